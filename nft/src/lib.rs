@@ -5,26 +5,263 @@
 extern crate pbc_contract_codegen;
 
 use create_type_spec_derive::CreateTypeSpec;
-use pbc_contract_common::address::{Address, Shortname};
-use pbc_contract_common::context::ContractContext;
+use pbc_contract_common::address::{Address, AddressType, Shortname};
+use pbc_contract_common::context::{CallbackContext, ContractContext};
 use pbc_contract_common::sorted_vec_map::{SortedVec, SortedVecMap};
 use pbc_contract_common::events::EventGroup;
+use read_write_rpc_derive::ReadWriteRPC;
 use read_write_state_derive::ReadWriteState;
 
-/// A permission to transfer and approve NFTs given from an NFT owner to a separate address, called an operator.
+/// Maximum byte length of each `UriMetadata` field accepted at mint.
+const MAX_METADATA_LEN: usize = 256;
+
+/// Schema version of the `UriMetadata` layout given to newly minted tokens.
+const METADATA_SCHEMA_VERSION: u16 = 1;
+
+/// Maximum number of owners returned by a single page of `all_owners_paged`.
+const MAX_PAGE_SIZE: u128 = 100;
+
+// Error messages. Each starts with a stable code clients can match on.
+const ERR_MALFORMED_TIMESTAMP: &str = "MPC-721-001: malformed timestamp";
+const ERR_NONEXISTENT: &str = "MPC-721-002: owner query for nonexistent token";
+const ERR_INCORRECT_OWNER: &str = "MPC-721-003: transfer from incorrect owner";
+const ERR_SOULBOUND: &str = "MPC-721-004: token is soulbound";
+const ERR_WALLET_MINT_LIMIT: &str = "MPC-721-005: wallet mint limit exceeded";
+const ERR_BLOCK_MINT_LIMIT: &str = "MPC-721-006: block mint limit exceeded";
+const ERR_MINT_TO_ZERO_ADDRESS: &str = "MPC-721-007: mint to the zero address";
+const ERR_MINT_COUNT_ZERO: &str = "MPC-721-008: mint count is zero";
+const ERR_TOKEN_ID_OVERFLOW: &str = "MPC-721-009: token id overflow";
+const ERR_TOKEN_EXISTS: &str = "MPC-721-010: token already exists";
+const ERR_FUNGIBLE: &str = "MPC-721-011: token is semi-fungible";
+const ERR_METADATA_TOO_LONG: &str = "MPC-721-012: metadata field too long";
+const ERR_NOT_OWNER: &str = "MPC-721-013: caller is not the contract owner";
+const ERR_UNKNOWN_TRANSFER_MODE: &str = "MPC-721-014: unknown transfer mode";
+const ERR_MISSING_PLACEHOLDER: &str = "MPC-721-015: uri_template is missing the {id} placeholder";
+const ERR_APPROVE_NONEXISTENT: &str = "MPC-721-016: approve for nonexistent token";
+const ERR_APPROVE_UNAUTHORIZED: &str =
+    "MPC-721-017: approve caller is not owner nor authorized operator";
+const ERR_APPROVE_TO_CALLER: &str = "MPC-721-018: approve to caller";
+const ERR_NO_OPERATOR_APPROVAL: &str = "MPC-721-019: no such operator approval";
+const ERR_TRANSFER_UNAUTHORIZED: &str = "MPC-721-020: transfer caller is not owner nor approved";
+const ERR_LOCKED: &str = "MPC-721-021: token is locked";
+const ERR_NOT_OWNED_BY_CONTRACT: &str = "MPC-721-022: token is not owned by the contract";
+const ERR_LOCK_UNAUTHORIZED: &str = "MPC-721-023: lock caller is not owner nor approved";
+const ERR_NOT_LOCKED: &str = "MPC-721-024: token is not locked";
+const ERR_UNLOCK_UNAUTHORIZED: &str = "MPC-721-025: unlock caller is not the locker";
+const ERR_NO_RECIPIENTS: &str = "MPC-721-026: no recipients";
+const ERR_MINT_UNAUTHORIZED: &str =
+    "MPC-721-027: mint only callable by the contract owner or an active delegate";
+const ERR_MINT_FEE_OVERFLOW: &str = "MPC-721-028: mint fee overflow";
+const ERR_RESERVED: &str = "MPC-721-029: token id is reserved";
+const ERR_NOT_RESERVED: &str = "MPC-721-030: token id is not reserved";
+const ERR_METADATA_FROZEN: &str = "MPC-721-031: metadata is frozen";
+const ERR_UPDATE_NONEXISTENT: &str = "MPC-721-032: metadata update for nonexistent token";
+const ERR_FREEZE_NONEXISTENT: &str = "MPC-721-033: freeze for nonexistent token";
+const ERR_EMPTY_NAME: &str = "MPC-721-034: name is empty";
+const ERR_EMPTY_SYMBOL: &str = "MPC-721-035: symbol is empty";
+const ERR_BURN_UNAUTHORIZED: &str = "MPC-721-036: burn caller is not owner nor approved";
+const ERR_BURN_NONEXISTENT: &str = "MPC-721-037: burn of nonexistent token";
+const ERR_EXPORT_NO_METADATA: &str = "MPC-721-038: export for token without metadata";
+const ERR_IMPORT_ORIGIN: &str = "MPC-721-039: caller is not an allowed import origin";
+const ERR_NOT_FUNGIBLE: &str = "MPC-721-040: token is not semi-fungible";
+const ERR_MINT_AMOUNT_ZERO: &str = "MPC-721-041: mint amount is zero";
+const ERR_QUANTITY_OVERFLOW: &str = "MPC-721-042: quantity overflow";
+const ERR_AMOUNT_EXCEEDS_BALANCE: &str = "MPC-721-043: transfer amount exceeds balance";
+const ERR_NOT_ALLOWLISTED: &str = "MPC-721-044: caller is not on the allowlist";
+const ERR_EXPIRED: &str = "MPC-721-045: token is expired";
+const ERR_NEGATIVE_GRACE_PERIOD: &str = "MPC-721-046: grace period is negative";
+const ERR_NO_EVENT_LISTENER: &str = "MPC-721-047: no event listener is set";
+const ERR_NO_PREVIOUS_OWNER: &str = "MPC-721-048: previous owner of token is unknown";
+
+/// `transfer_mode` in which only the contract owner may call `transfer_from`.
+const TRANSFER_MODE_CUSTODIAL: u8 = 0;
+
+/// `transfer_mode` in which `transfer_from` follows the usual owner and approval rules.
+const TRANSFER_MODE_STANDARD: u8 = 1;
+
+/// The address the contract owner is set to when ownership is renounced.
+/// It cannot sign transactions, so owner-gated actions can never be called again.
+const ZERO_ADDRESS: Address = Address {
+    address_type: AddressType::Account,
+    identifier: [0u8; 20],
+};
+
+/// Identifier of an NFT in this contract.
+///
+/// Kept distinct from plain `u128` counts so token ids cannot be mixed up with other numeric arguments.
+/// Serializes exactly like the wrapped `u128`.
+#[derive(ReadWriteState, ReadWriteRPC, CreateTypeSpec, PartialEq, Copy, Clone, Ord, PartialOrd, Eq)]
+pub struct TokenId {
+    id: u128,
+}
+
+impl From<u128> for TokenId {
+    fn from(id: u128) -> Self {
+        TokenId { id }
+    }
+}
+
+impl TokenId {
+    /// Get the raw `u128` value of the id.
+    pub fn into_inner(self) -> u128 {
+        self.id
+    }
+}
+
+/// A time-boxed right to mint, given by the contract owner to another address.
+#[derive(ReadWriteState, CreateTypeSpec, PartialEq, Copy, Clone)]
+struct MintDelegation {
+    /// Address allowed to mint.
+    delegate: Address,
+    /// Unix time in seconds after which the delegation lapses.
+    until: i64,
+}
+
+/// The times of a token's `UriMetadata` parsed into unix seconds. 0 means the field is empty.
+#[derive(ReadWriteState, CreateTypeSpec, PartialEq, Copy, Clone)]
+struct UriMetadataParsed {
+    /// Parsed `mpg_time`.
+    mpg_time: i64,
+    /// Parsed `exp_time`.
+    exp_time: i64,
+}
+
+impl UriMetadataParsed {
+    /// Parse the times of `metadata`. Throws if a non-empty time is not a unix timestamp.
+    fn parse(metadata: &UriMetadata) -> Self {
+        UriMetadataParsed {
+            mpg_time: parse_time(&metadata.mpg_time),
+            exp_time: parse_time(&metadata.exp_time),
+        }
+    }
+}
+
+/// Convert a block production time, which is in milliseconds, to unix seconds.
+/// Every expiry in the contract is compared in seconds.
+fn unix_seconds(block_production_time: i64) -> i64 {
+    block_production_time / 1000
+}
+
+/// Parse a time string holding unix seconds, treating the empty string as 0.
+/// Throws on malformed input.
+fn parse_time(value: &str) -> i64 {
+    if value.is_empty() {
+        return 0;
+    }
+    match value.trim().parse::<i64>() {
+        Err(_) => panic!("{}", ERR_MALFORMED_TIMESTAMP),
+        Ok(time) => time,
+    }
+}
+
+/// Throws if a field of `metadata` is longer than [`MAX_METADATA_LEN`] bytes.
+fn check_metadata_len(metadata: &UriMetadata) {
+    if metadata.status.len() > MAX_METADATA_LEN
+        || metadata.mpg_time.len() > MAX_METADATA_LEN
+        || metadata.exp_time.len() > MAX_METADATA_LEN
+    {
+        panic!("{}", ERR_METADATA_TOO_LONG)
+    }
+}
+
+/// Validate the recipient and metadata of a new token, shared by every mint path.
+/// Throws if `to` is the zero address or a field of `metadata` is too long.
+///
+/// ### Returns:
+///
+/// The [`UriMetadata`] to store.
+fn validated_metadata(to: Address, metadata: UriMetadata) -> UriMetadata {
+    if to == ZERO_ADDRESS {
+        panic!("{}", ERR_MINT_TO_ZERO_ADDRESS)
+    }
+    check_metadata_len(&metadata);
+    metadata
+}
+
+/// An owner and operator pair, used as the key of operator approvals and scoped approvals.
 #[derive(ReadWriteState, CreateTypeSpec, PartialEq, Copy, Clone, Ord, PartialOrd, Eq)]
-struct OperatorApproval {
+struct OperatorScope {
     /// NFT owner.
     owner: Address,
-    /// Operator of the owner's tokens.u12
+    /// Operator of the owner's tokens.
     operator: Address,
 }
 
-#[derive(ReadWriteState, CreateTypeSpec, PartialEq, Clone, Ord, PartialOrd, Eq)]
-struct UriMetadata {
+/// The contract and token id an imported token was exported from.
+#[derive(ReadWriteState, ReadWriteRPC, CreateTypeSpec, PartialEq, Copy, Clone)]
+struct ImportOrigin {
+    /// The NFT contract the token was exported from.
+    contract: Address,
+    /// The id of the token in `contract`.
+    token_id: TokenId,
+}
+
+/// Everything stored for an NFT apart from its transfer history, carried by {export_token}
+/// so that a failed export restores the token exactly.
+#[derive(ReadWriteRPC, CreateTypeSpec, Clone)]
+struct TokenRecord {
+    /// Owner of the token.
+    owner: Address,
+    /// Metadata of the token.
+    metadata: UriMetadata,
+    /// Version of the layout of the metadata.
+    schema_version: u16,
+    /// Block production time at which the token was minted.
+    minted_at: i64,
+    /// Address approved to transfer the token, if any.
+    approved: Option<Address>,
+    /// Whether the token is soulbound.
+    soulbound: bool,
+    /// Whether the metadata of the token is frozen individually.
+    frozen: bool,
+    /// Address holding a lock on the token, if any.
+    locker: Option<Address>,
+    /// Where the token was imported from, if it was.
+    imported_from: Option<ImportOrigin>,
+}
+
+/// A transfer sent to the user contract, carried to {transfer_product_result} so that
+/// the token can be moved back with its approval and lock if the user contract refuses it.
+#[derive(ReadWriteRPC, CreateTypeSpec, Clone)]
+struct PendingTransfer {
+    /// Owner of the token before the transfer.
+    from: Address,
+    /// Owner of the token after the transfer.
+    to: Address,
+    /// The transferred token.
+    token_id: TokenId,
+    /// Address approved to transfer the token before the transfer, if any.
+    approved: Option<Address>,
+    /// Address holding a lock on the token before the transfer, if any.
+    locker: Option<Address>,
+}
+
+/// The holder of part of a semi-fungible token, used as the key of its balance.
+#[derive(ReadWriteState, CreateTypeSpec, PartialEq, Copy, Clone, Ord, PartialOrd, Eq)]
+struct FungibleHolding {
+    /// The semi-fungible token.
+    token_id: TokenId,
+    /// The holder of the balance.
+    holder: Address,
+}
+
+/// A single entry in the transfer history of a token.
+#[derive(ReadWriteState, CreateTypeSpec, PartialEq, Copy, Clone)]
+struct TransferRecord {
+    /// Previous owner.
+    from: Address,
+    /// New owner.
+    to: Address,
+    /// Block production time of the transfer.
+    time: i64,
+}
+
+/// Metadata stored for every NFT.
+#[derive(ReadWriteState, ReadWriteRPC, CreateTypeSpec, PartialEq, Clone, Ord, PartialOrd, Eq)]
+pub struct UriMetadata {
     status: String,
     mpg_time: String,
-    exp_time: String
+    exp_time: String,
 }
 
 /// State of the contract.
@@ -36,62 +273,417 @@ pub struct NFTContractState {
     symbol: String,
     user_contract_accress: Address,
     /// Mapping from token_id to the owner of the token.
-    owners: SortedVecMap<u128, Address>,
+    owners: SortedVecMap<TokenId, Address>,
     /// Mapping from token_id to the approved address who can transfer the token.
-    token_approvals: SortedVecMap<u128, Address>,
-    /// Containing approved operators of owners. Operators can transfer and change approvals on all tokens owned by owner.
-    operator_approvals: SortedVec<OperatorApproval>,
+    token_approvals: SortedVecMap<TokenId, Address>,
+    /// Mapping from owner and operator to the unix time in seconds after which the approval
+    /// lapses, 0 for never. Operators can transfer and change approvals on all tokens owned by owner.
+    operator_approvals: SortedVecMap<OperatorScope, i64>,
     /// Template which the uri's of the NFTs fit into.
     uri_template: String,
     /// Mapping from token_id to the URI of the token.
-    token_uri_details: SortedVecMap<u128, UriMetadata>,
+    token_uri_details: SortedVecMap<TokenId, UriMetadata>,
+    /// Mapping from token_id to the version of the layout of its metadata,
+    /// `METADATA_SCHEMA_VERSION` for tokens minted here.
+    metadata_versions: SortedVecMap<TokenId, u16>,
     /// Owner of the contract. Is allowed to mint new NFTs.
     contract_owner: Address,
-    total_count: u128
+    total_count: u128,
+    /// Fee per token paid to the contract owner by the caller of `batch_mint`. 0 disables the fee.
+    mint_fee: u128,
+    /// MPC-20 token contract the mint fee is paid in.
+    fee_token: Address,
+    /// Expiry time given to minted tokens when no `exp_time` is provided.
+    default_exp_time: String,
+    /// Mapping from token_id to the block production time the token was minted at.
+    minted_at: SortedVecMap<TokenId, i64>,
+    /// Mapping from owner to the number of tokens they own. Derived from `owners`.
+    balances: SortedVecMap<Address, u128>,
+    /// Version of the state layout, bumped by `migrate`.
+    state_version: u32,
+    /// Maximum number of tokens a wallet may hold through minting, or none for no limit.
+    max_per_wallet: Option<u128>,
+    /// Address temporarily allowed to `batch_mint` besides the contract owner, if any.
+    mint_delegation: Option<MintDelegation>,
+    /// Mapping from token_id to its most recent transfers, oldest first.
+    transfer_log: SortedVecMap<TokenId, Vec<TransferRecord>>,
+    /// Maximum number of entries kept in the transfer history of each token.
+    transfer_log_limit: u32,
+    /// Mapping from token_id to the parsed times of its metadata.
+    parsed_times: SortedVecMap<TokenId, UriMetadataParsed>,
+    /// Tokens that can never be transferred, only burned.
+    soulbound: SortedVec<TokenId>,
+    /// Mapping from token_id to the address that locked it, if locked.
+    locked: SortedVecMap<TokenId, Address>,
+    /// Contract notified of ownership and operator approval changes and forced transfers, if any.
+    event_listener: Option<Address>,
+    /// Tokens whose metadata can no longer be updated.
+    frozen_metadata: SortedVec<TokenId>,
+    /// Whether the metadata of every token is permanently frozen.
+    metadata_frozen_all: bool,
+    /// Maximum number of tokens `batch_mint` may mint within one block, or none for no limit.
+    max_mints_per_block: Option<u128>,
+    /// Block time of the most recent `batch_mint`.
+    last_mint_block: i64,
+    /// Number of tokens minted by `batch_mint` in `last_mint_block`.
+    mints_this_block: u128,
+    /// Token ids allocated by `reserve` that have not been minted yet.
+    reserved: SortedVec<TokenId>,
+    /// Who may call `transfer_from`, either [`TRANSFER_MODE_CUSTODIAL`] or [`TRANSFER_MODE_STANDARD`].
+    transfer_mode: u8,
+    /// Number of transfers that cleared an existing token approval. Never decreases.
+    approvals_cleared: u128,
+    /// Nonce of the last product call sent to the user contract, which rejects replayed calls.
+    user_contract_nonce: u128,
+    /// Mapping from token_id of a semi-fungible token to the total quantity minted.
+    quantities: SortedVecMap<TokenId, u128>,
+    /// Mapping from holding to the quantity of a semi-fungible token held.
+    fungible_balances: SortedVecMap<FungibleHolding, u128>,
+    /// Operators allowed to manage only the tokens of an owner whose metadata has a given status.
+    scoped_approvals: SortedVecMap<OperatorScope, String>,
+    /// NFT contracts allowed to call `mint_imported`.
+    import_origins: SortedVec<Address>,
+    /// Mapping from token_id of an imported token to where it was exported from.
+    imported_from: SortedVecMap<TokenId, ImportOrigin>,
+    /// Addresses that may each mint one token with `allowlist_mint`.
+    allowlist: SortedVec<Address>,
+    /// Shortname of `mint_product` on the user contract.
+    mint_shortname: u32,
+    /// Shortname of `transfer_product` on the user contract.
+    transfer_shortname: u32,
+    /// Seconds after `exp_time` during which a token still counts as unexpired.
+    grace_period_seconds: i64,
+}
+
+/// Shortname of `transfer_product` on the user contract, as configured at `initialize`.
+/// Called with the arguments `from, to, product_address, product_id, note, nonce`.
+#[inline]
+fn transfer_product(state: &NFTContractState) -> Shortname {
+    Shortname::from_u32(state.transfer_shortname)
+}
+
+/// Shortname of `transfer` on an MPC-20 token contract.
+/// Called with the arguments `to, amount`.
+#[inline]
+fn token_transfer() -> Shortname {
+    Shortname::from_u32(0x01)
 }
 
+/// Shortname of `transfer_from` on an MPC-20 token contract.
+/// Called with the arguments `from, to, amount`.
 #[inline]
-fn transfer_product() -> Shortname {
+fn token_transfer_from() -> Shortname {
+    Shortname::from_u32(0x03)
+}
+
+/// Shortname of `burn_product` on the user contract.
+/// Called with the arguments `product_address, product_ids`.
+#[inline]
+fn burn_product() -> Shortname {
+    Shortname::from_u32(0x07)
+}
+
+/// Shortname of `mint_product` on the user contract, as configured at `initialize`.
+/// Called with the arguments
+/// `to, product_address, product_id, status, mpg_time, exp_time, note, nonce`.
+#[inline]
+fn mint_product(state: &NFTContractState) -> Shortname {
+    Shortname::from_u32(state.mint_shortname)
+}
+
+/// Shortname of `mint_imported` on another NFT contract.
+/// Called with the arguments `to, metadata, origin_contract, origin_token_id`.
+#[inline]
+fn mint_imported() -> Shortname {
+    Shortname::from_u32(0x22)
+}
+
+/// Shortname of `ownership_changed` on the event listener.
+/// Called with the arguments `old_owner, new_owner`.
+#[inline]
+fn ownership_changed() -> Shortname {
+    Shortname::from_u32(0x01)
+}
+
+/// Shortname of `approval_for_all` on the event listener, sent when an operator approval
+/// is granted or revoked. Called with the arguments `owner, operator, approved, balance`,
+/// where `balance` is the number of tokens owned by `owner` at the time of the change.
+#[inline]
+fn approval_for_all() -> Shortname {
     Shortname::from_u32(0x02)
 }
 
+/// Shortname of `force_transfer_audit` on the event listener, sent for every {force_transfer}.
+/// Called with the arguments `from, to, token_id, reason`.
 #[inline]
-fn mint_product() -> Shortname {
+fn force_transfer_audit() -> Shortname {
+    Shortname::from_u32(0x04)
+}
+
+/// Shortname of `approvals_revoked` on the event listener, sent by {revoke_approvals}.
+/// Called with the arguments `caller, count`.
+#[inline]
+fn approvals_revoked() -> Shortname {
+    Shortname::from_u32(0x05)
+}
+
+/// Shortname of `approval_for_all_many` on the event listener, sent by {set_approval_for_all_many}.
+/// Called with the arguments `owner, operators, approved, balance`.
+#[inline]
+fn approval_for_all_many() -> Shortname {
+    Shortname::from_u32(0x06)
+}
+
+/// Shortname of `minting_delegated` on the event listener, sent when a mint delegation is
+/// granted, replaced or revoked. Called with the arguments `delegate, until`; a revocation
+/// by {renounce_ownership} is sent as the zero address with `until` 0.
+#[inline]
+fn minting_delegated() -> Shortname {
+    Shortname::from_u32(0x07)
+}
+
+/// Shortname of `approval_for_all_noop` on the event listener, sent when a revocation
+/// found no approval to remove. Called with the arguments `owner, operator`.
+#[inline]
+fn approval_for_all_noop() -> Shortname {
     Shortname::from_u32(0x03)
 }
 
 impl NFTContractState {
+    /// Check whether an address is the owner of the contract.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `addr`: [`Address`] The address to check.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if `addr` owns the contract, false otherwise.
+    pub fn is_contract_owner(&self, addr: Address) -> bool {
+        self.contract_owner == addr
+    }
+
+    /// Get the owner of the contract.
+    ///
+    /// ### Returns:
+    ///
+    /// The [`Address`] of the contract owner.
+    pub fn contract_owner(&self) -> Address {
+        self.contract_owner
+    }
+
+    /// Get the basic facts about the collection in one call.
+    ///
+    /// ### Returns:
+    ///
+    /// A tuple of `(name, symbol, uri_template, contract_owner, total_count)`.
+    pub fn collection_info(&self) -> (String, String, String, Address, u128) {
+        (
+            self.name.clone(),
+            self.symbol.clone(),
+            self.uri_template.clone(),
+            self.contract_owner,
+            self.total_count,
+        )
+    }
+
+    /// Get the descriptive header of the collection in one call.
+    /// The contract stores no contract-level uri, so `contract_uri` is an empty string.
+    ///
+    /// ### Returns:
+    ///
+    /// A tuple of `(name, symbol, uri_template, contract_uri)`.
+    pub fn collection_header(&self) -> (String, String, String, String) {
+        (
+            self.name.clone(),
+            self.symbol.clone(),
+            self.uri_template.clone(),
+            String::new(),
+        )
+    }
+
+    /// Check whether an address may currently use `batch_mint`.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `addr`: [`Address`] The address to check.
+    ///
+    /// * `now`: [`i64`] The current time in unix seconds.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if `addr` owns the contract or holds an active mint delegation.
+    pub fn can_mint(&self, addr: Address, now: i64) -> bool {
+        self.is_contract_owner(addr)
+            || self
+                .mint_delegation
+                .is_some_and(|delegation| delegation.delegate == addr && now <= delegation.until)
+    }
+
     /// Find the owner of an NFT.
     /// Throws if no such token exists.
     ///
     /// ### Parameters:
     ///
-    /// * `token_id`: [`u128`] The identifier for an NFT.
+    /// * `token_id`: [`TokenId`] The identifier for an NFT.
     ///
     /// ### Returns:
     ///
     /// An [`Address`] for the owner of the NFT.
-    pub fn owner_of(&self, token_id: u128) -> Address {
+    pub fn owner_of(&self, token_id: TokenId) -> Address {
         let owner_opt = self.owners.get(&token_id);
         match owner_opt {
-            None => panic!("MPC-721: owner query for nonexistent token"),
+            None => panic!("{}", ERR_NONEXISTENT),
             Some(owner) => *owner,
         }
     }
 
+    /// Get the total quantity of a token. Unique NFTs have a quantity of 1.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`] The token to get the quantity of.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] The quantity in existence, 0 if the token does not exist.
+    pub fn quantity_of(&self, token_id: TokenId) -> u128 {
+        match self.quantities.get(&token_id) {
+            Some(quantity) => *quantity,
+            None if self.exists(token_id) => 1,
+            None => 0,
+        }
+    }
+
+    /// Get the quantity of a token held by an address. Unique NFTs are held with a quantity of 1.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`] The token to get the quantity of.
+    ///
+    /// * `holder`: [`Address`] The address holding the quantity.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] The quantity held by `holder`, possibly 0.
+    pub fn amount_of(&self, token_id: TokenId, holder: Address) -> u128 {
+        if self.quantities.contains_key(&token_id) {
+            let holding = FungibleHolding { token_id, holder };
+            self.fungible_balances.get(&holding).copied().unwrap_or(0)
+        } else if self.owners.get(&token_id) == Some(&holder) {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Get the highest token id allocated so far. Burned ids stay allocated.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] The last allocated token id, 0 if nothing was minted.
+    pub fn last_token_id(&self) -> u128 {
+        self.total_count
+    }
+
+    /// Count the NFTs currently in existence, i.e. minted and not burned.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] number of existing NFTs.
+    pub fn total_supply(&self) -> u128 {
+        self.owners.len() as u128
+    }
+
+    /// Snapshot the owner of every existing NFT, e.g. for an airdrop.
+    /// Reflects the current state only; later mints, transfers and burns are not included.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`Vec<(u128, Address)>`] of `(token_id, owner)` pairs in ascending id order.
+    pub fn all_owners(&self) -> Vec<(u128, Address)> {
+        self.owners
+            .iter()
+            .map(|(token_id, owner)| (token_id.into_inner(), *owner))
+            .collect()
+    }
+
+    /// List one page of the `all_owners` snapshot, for collections too large to read at once.
+    /// Pages are only consistent with each other if the ledger did not change between reads.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `offset`: [`u128`] The number of pairs to skip.
+    ///
+    /// * `limit`: [`u128`] The maximum number of pairs to return, clamped to [`MAX_PAGE_SIZE`].
+    ///
+    /// ### Returns:
+    ///
+    /// A [`Vec<(u128, Address)>`] of `(token_id, owner)` pairs in ascending id order,
+    /// empty past the end of the ledger.
+    pub fn all_owners_paged(&self, offset: u128, limit: u128) -> Vec<(u128, Address)> {
+        let offset = usize::try_from(offset).unwrap_or(usize::MAX);
+        let limit = limit.min(MAX_PAGE_SIZE) as usize;
+        self.owners
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|(token_id, owner)| (token_id.into_inner(), *owner))
+            .collect()
+    }
+
+    /// Count the NFTs owned by an address.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `owner`: [`Address`] The address to count NFTs for.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] number of NFTs owned by `owner`, possibly 0.
+    pub fn balance_of(&self, owner: Address) -> u128 {
+        self.balances.get(&owner).copied().unwrap_or(0)
+    }
+
     /// Get the approved address for a single NFT.
     ///
     /// ### Parameters:
     ///
-    /// * `token_id`: [`u128`] The NFT to find the approved address for.
+    /// * `token_id`: [`TokenId`] The NFT to find the approved address for.
     ///
     /// ### Returns:
     ///
     /// An [`Option<Address>`] The approved address for this NFT, or none if there is none.
-    pub fn get_approved(&self, token_id: u128) -> Option<Address> {
+    pub fn get_approved(&self, token_id: TokenId) -> Option<Address> {
         self.token_approvals.get(&token_id).copied()
     }
 
+    /// Get the number of transfers that cleared an existing token approval.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] The number of approvals cleared by transfers so far.
+    pub fn approvals_cleared_total(&self) -> u128 {
+        self.approvals_cleared
+    }
+
+    /// Count the NFTs of an owner that have an approved address.
+    /// Walks every approval in the contract, so the cost is O(n) in the number of approvals.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `owner`: [`Address`] The address to count approved NFTs for.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] number of NFTs owned by `owner` with an approved address, possibly 0.
+    pub fn approval_count_of(&self, owner: Address) -> u128 {
+        self.token_approvals
+            .iter()
+            .filter(|(token_id, _)| self.owners.get(token_id) == Some(&owner))
+            .count() as u128
+    }
+
     /// Query if an address is an authorized operator for another address.
     ///
     /// ### Parameters:
@@ -100,130 +692,3034 @@ impl NFTContractState {
     ///
     /// * `operator`: [`Address`] The address that acts on behalf of the owner.
     ///
+    /// * `now`: [`i64`] The current time in unix seconds, used to discard lapsed approvals.
+    ///
     /// ### Returns:
     ///
     /// A [`bool`] true if `operator` is an approved operator for `owner`, false otherwise.
-    pub fn is_approved_for_all(&self, owner: Address, operator: Address) -> bool {
-        let as_operator_approval: OperatorApproval = OperatorApproval { owner, operator };
-        self.operator_approvals.contains(&as_operator_approval)
+    pub fn is_approved_for_all(&self, owner: Address, operator: Address, now: i64) -> bool {
+        match self.operator_approvals.get(&OperatorScope { owner, operator }) {
+            None => false,
+            Some(expires_at) => *expires_at == 0 || now <= *expires_at,
+        }
     }
 
-    /// Helper function to check whether a tokenId exists.
+    /// Get the metadata stored for a single NFT.
     ///
-    /// Tokens start existing when they are minted (`mint`),
-    /// and stop existing when they are burned (`burn`).
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`] The NFT to find the metadata for.
+    ///
+    /// ### Returns:
+    ///
+    /// An [`Option<UriMetadata>`] The metadata of this NFT, or none if the token does not exist.
+    pub fn metadata_of(&self, token_id: TokenId) -> Option<UriMetadata> {
+        self.token_uri_details.get(&token_id).cloned()
+    }
+
+    /// Get the owner, approved address and metadata of a single NFT in one call.
     ///
     /// ### Parameters:
     ///
-    /// * `token_id`: [`u128`] The tokenId that is checked.
+    /// * `token_id`: [`TokenId`] The NFT to describe.
     ///
     /// ### Returns:
     ///
-    /// A [`bool`] True if `token_id` is in use, false otherwise.
-    pub fn exists(&self, token_id: u128) -> bool {
-        let owner = self.owners.get(&token_id);
-        owner.is_some()
+    /// An [`Option`] with a tuple of the owner, the approved address if any, and the
+    /// metadata of this NFT, or none if the token does not exist.
+    pub fn token_detail(
+        &self,
+        token_id: TokenId,
+    ) -> Option<(Address, Option<Address>, UriMetadata)> {
+        let owner = *self.owners.get(&token_id)?;
+        let metadata = self.metadata_of(token_id)?;
+        Some((owner, self.get_approved(token_id), metadata))
     }
 
-    /// Helper function to check whether a spender is owner or approved for a given token.
-    /// Throws if token_id does not exist.
+    /// Get the schema version of the metadata stored for a single NFT.
     ///
     /// ### Parameters:
     ///
-    /// * `spender`: [`Address`] The address to check ownership for.
+    /// * `token_id`: [`TokenId`] The NFT to find the schema version for.
+    ///
+    /// ### Returns:
+    ///
+    /// An [`Option<u16>`] The schema version of the metadata, or none if the token does not exist.
+    pub fn metadata_schema_version(&self, token_id: TokenId) -> Option<u16> {
+        self.metadata_versions.get(&token_id).copied()
+    }
+
+    /// Check whether the metadata of a single NFT is frozen, either by itself or
+    /// because the metadata of the whole collection is frozen.
     ///
-    /// * `token_id`: [`u128`] The tokenId which is checked.
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`] The NFT to check.
     ///
     /// ### Returns:
     ///
-    /// A [`bool`] True if `token_id` is owned or approved for `spender`, false otherwise.
-    pub fn is_approved_or_owner(&self, spender: Address, token_id: u128) -> bool {
-        let owner = self.owner_of(token_id);
-        spender == owner
-            || self.get_approved(token_id) == Some(spender)
-            || self.is_approved_for_all(owner, spender)
+    /// A [`bool`] True if the metadata of `token_id` can no longer be updated, false otherwise.
+    pub fn is_frozen(&self, token_id: TokenId) -> bool {
+        self.metadata_frozen_all || self.frozen_metadata.contains(&token_id)
     }
 
-    /// Mutates the state by approving `to` to operate on `token_id`.
-    /// None indicates there is no approved address.
+    /// Get the block production time a single NFT was minted at.
     ///
     /// ### Parameters:
     ///
-    /// * `approved`: [`Option<Address>`], The new approved NFT controller.
+    /// * `token_id`: [`TokenId`] The NFT to find the mint time for.
     ///
-    /// * `token_id`: [`u128`], The NFT to approve.
-    pub fn _approve(&mut self, approved: Option<Address>, token_id: u128) {
-        if let Some(appr) = approved {
-            self.token_approvals.insert(token_id, appr);
-        } else {
-            self.token_approvals.remove(&token_id);
-        }
+    /// ### Returns:
+    ///
+    /// An [`Option<i64>`] The mint time of this NFT, or none if the token does not exist.
+    pub fn minted_at(&self, token_id: TokenId) -> Option<i64> {
+        self.minted_at.get(&token_id).copied()
     }
 
-    /// Mutates the state by transferring `token_id` from `from` to `to`.
-    /// As opposed to {transfer_from}, this imposes no restrictions on `ctx.sender`.
+    /// List every NFT whose metadata has the given status. Semi-fungible tokens are not listed.
     ///
-    /// Throws if `from` is not the owner of `token_id`.
+    /// Scans all tokens, so it is meant for off-chain and admin use rather than inside actions.
     ///
     /// ### Parameters:
     ///
-    /// * `from`: [`Address`], The current owner of the NFT
+    /// * `status`: [`&str`] The status to look for.
     ///
-    /// * `to`: [`Address`], The new owner
+    /// ### Returns:
     ///
-    /// * `token_id`: [`u128`], The NFT to transfer
-    pub fn _transfer(&mut self, from: Address, to: Address, token_id: u128) {
-        if self.owner_of(token_id) != from {
-            panic!("MPC-721: transfer from incorrect owner")
-        } else {
-            // clear approvals from the previous owner
-            self._approve(None, token_id);
-            self.owners.insert(token_id, to);
+    /// A [`Vec<TokenId>`] of the matching NFTs in id order.
+    pub fn tokens_with_status(&self, status: &str) -> Vec<TokenId> {
+        self.token_uri_details
+            .iter()
+            .filter(|(token_id, metadata)| {
+                metadata.status == status && !self.quantities.contains_key(token_id)
+            })
+            .map(|(token_id, _)| *token_id)
+            .collect()
+    }
+
+    /// Get where an imported NFT was exported from.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`] The NFT to find the origin for.
+    ///
+    /// ### Returns:
+    ///
+    /// An [`Option`] of the origin contract and the id of the token there,
+    /// or none if the token was not imported.
+    pub fn origin_of(&self, token_id: TokenId) -> Option<(Address, TokenId)> {
+        self.imported_from
+            .get(&token_id)
+            .map(|origin| (origin.contract, origin.token_id))
+    }
+
+    /// Get the recorded transfers of a single NFT.
+    ///
+    /// Only the most recent `transfer_log_limit` transfers are kept.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`] The NFT to find the history for.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`Vec`] of `(from, to, block_production_time)` entries, oldest first.
+    pub fn transfer_history(&self, token_id: TokenId) -> Vec<(Address, Address, i64)> {
+        match self.transfer_log.get(&token_id) {
+            None => Vec::new(),
+            Some(log) => log
+                .iter()
+                .map(|record| (record.from, record.to, record.time))
+                .collect(),
         }
     }
-}
 
-/// Initial function to bootstrap the contracts state.
-///
-/// ### Parameters:
+    /// Check whether an NFT has expired, i.e. its grace period after `exp_time` has passed.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`] The NFT to check.
+    ///
+    /// * `now`: [`i64`] The reference time in unix seconds.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if the NFT has a parsed `exp_time` and `exp_time + grace_period_seconds`
+    /// lies before `now`, false otherwise.
+    pub fn is_expired(&self, token_id: TokenId, now: i64) -> bool {
+        match self.parsed_times.get(&token_id) {
+            None => false,
+            Some(parsed) => {
+                parsed.exp_time != 0
+                    && parsed.exp_time.saturating_add(self.grace_period_seconds) < now
+            }
+        }
+    }
+
+    /// List every NFT whose `exp_time` lies before a given time, e.g. as a worklist for `burn_batch`.
+    ///
+    /// Uses the times parsed at mint. Tokens with an empty `exp_time` never expire.
+    /// Semi-fungible tokens are not listed, since they cannot be burned as NFTs.
+    /// Scans all tokens, so it is meant for off-chain and admin use rather than inside actions.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `now`: [`i64`] The reference time in unix seconds.
+    ///
+    /// ### Returns:
+    ///
+    /// A tuple of the expired NFTs and the NFTs that have no parsed times.
+    pub fn expired_tokens(&self, now: i64) -> (Vec<TokenId>, Vec<TokenId>) {
+        let mut expired = Vec::new();
+        let mut unparsed = Vec::new();
+        for (token_id, _) in self.token_uri_details.iter() {
+            if self.quantities.contains_key(token_id) {
+                continue;
+            }
+            if !self.parsed_times.contains_key(token_id) {
+                unparsed.push(*token_id);
+            } else if self.is_expired(*token_id, now) {
+                expired.push(*token_id);
+            }
+        }
+        (expired, unparsed)
+    }
+
+    /// List every operator approved by an owner.
+    ///
+    /// Approvals that have lapsed are still listed until they are revoked or replaced.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `owner`: [`Address`] The address that owns the NFTs.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`Vec<Address>`] of the operators of `owner`, empty if there are none.
+    pub fn operators_of(&self, owner: Address) -> Vec<Address> {
+        self.operator_approvals
+            .iter()
+            .filter(|(scope, _)| scope.owner == owner)
+            .map(|(scope, _)| scope.operator)
+            .collect()
+    }
+
+    /// Helper function to check whether a tokenId exists.
+    ///
+    /// Tokens start existing when they are minted (`mint`),
+    /// and stop existing when they are burned (`burn`).
+    ///
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`] The tokenId that is checked.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if `token_id` is in use, false otherwise.
+    pub fn exists(&self, token_id: TokenId) -> bool {
+        let owner = self.owners.get(&token_id);
+        owner.is_some()
+    }
+
+    /// Helper function to check whether each of a list of tokenIds exists.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `token_ids`: [`&[TokenId]`] The tokenIds that are checked.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`Vec<bool>`] with, for every id in `token_ids` in order, true if it is in use.
+    pub fn exist_many(&self, token_ids: &[TokenId]) -> Vec<bool> {
+        token_ids.iter().map(|token_id| self.exists(*token_id)).collect()
+    }
+
+    /// Helper function to check whether a spender is owner or approved for a given token.
+    /// Throws if token_id does not exist.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `spender`: [`Address`] The address to check ownership for.
+    ///
+    /// * `token_id`: [`TokenId`] The tokenId which is checked.
+    ///
+    /// * `now`: [`i64`] The current time in unix seconds.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if `token_id` is owned or approved for `spender`, false otherwise.
+    pub fn is_approved_or_owner(&self, spender: Address, token_id: TokenId, now: i64) -> bool {
+        let owner = self.owner_of(token_id);
+        spender == owner
+            || self.get_approved(token_id) == Some(spender)
+            || self.is_approved_for_all(owner, spender, now)
+            || self.is_scoped_operator(owner, spender, token_id)
+    }
+
+    /// Helper function to check whether an operator's scoped approval covers a token,
+    /// i.e. whether the status of the token matches the scope granted by its owner.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `owner`: [`Address`] The owner of the token.
+    ///
+    /// * `operator`: [`Address`] The address to check the scope for.
+    ///
+    /// * `token_id`: [`TokenId`] The tokenId which is checked.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if `operator` has a scope matching the status of `token_id`, false otherwise.
+    pub fn is_scoped_operator(&self, owner: Address, operator: Address, token_id: TokenId) -> bool {
+        match (
+            self.scoped_approvals.get(&OperatorScope { owner, operator }),
+            self.token_uri_details.get(&token_id),
+        ) {
+            (Some(status), Some(metadata)) => *status == metadata.status,
+            _ => false,
+        }
+    }
+
+    /// Helper function to check whether a spender is owner or approved for every one of a list of tokens.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `spender`: [`Address`] The address to check ownership for.
+    ///
+    /// * `token_ids`: [`&[TokenId]`] The tokenIds which are checked.
+    ///
+    /// * `now`: [`i64`] The current time in unix seconds.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if every token exists and is owned or approved for `spender`, false otherwise.
+    pub fn all_approved_or_owner(&self, spender: Address, token_ids: &[TokenId], now: i64) -> bool {
+        token_ids.iter().all(|token_id| {
+            self.exists(*token_id) && self.is_approved_or_owner(spender, *token_id, now)
+        })
+    }
+
+    /// Resolve every way a spender may be authorized for a token.
+    /// Throws if token_id does not exist.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`] The tokenId which is checked.
+    ///
+    /// * `spender`: [`Address`] The address to check permissions for.
+    ///
+    /// * `now`: [`i64`] The current time in unix seconds.
+    ///
+    /// ### Returns:
+    ///
+    /// A tuple of `(is_owner, is_approved, is_operator)` for `spender` on `token_id`.
+    pub fn permissions_of(
+        &self,
+        token_id: TokenId,
+        spender: Address,
+        now: i64,
+    ) -> (bool, bool, bool) {
+        let owner = self.owner_of(token_id);
+        (
+            spender == owner,
+            self.get_approved(token_id) == Some(spender),
+            self.is_approved_for_all(owner, spender, now),
+        )
+    }
+
+    /// Mutates the state by approving `to` to operate on `token_id`.
+    /// None indicates there is no approved address.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `approved`: [`Option<Address>`], The new approved NFT controller.
+    ///
+    /// * `token_id`: [`TokenId`], The NFT to approve.
+    pub fn _approve(&mut self, approved: Option<Address>, token_id: TokenId) {
+        if let Some(appr) = approved {
+            self.token_approvals.insert(token_id, appr);
+        } else {
+            self.token_approvals.remove(&token_id);
+        }
+    }
+
+    /// Mutates the state by granting or revoking `operator` as an operator of `owner`.
+    /// Any previous approval of the pair is replaced.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `owner`: [`Address`], The owner of the tokens.
+    ///
+    /// * `operator`: [`Address`], The operator to grant or revoke.
+    ///
+    /// * `approved`: [`bool`], True to grant the approval, false to revoke it.
+    ///
+    /// * `expires_at`: [`i64`], Unix time in seconds after which a granted approval lapses, 0 for never.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if an approval of the pair existed before the change, false otherwise.
+    pub fn _set_approval_for_all(
+        &mut self,
+        owner: Address,
+        operator: Address,
+        approved: bool,
+        expires_at: i64,
+    ) -> bool {
+        let scope = OperatorScope { owner, operator };
+        let existed = self.operator_approvals.remove(&scope).is_some();
+        if approved {
+            self.operator_approvals.insert(scope, expires_at);
+        }
+        existed
+    }
+
+    /// Mutates the state by transferring `token_id` from `from` to `to`.
+    /// As opposed to {transfer_from}, this imposes no restrictions on `ctx.sender`.
+    ///
+    /// Throws if `from` is not the owner of `token_id`, or if `token_id` is soulbound.
+    /// A transfer to the current owner leaves the state unchanged.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `from`: [`Address`], The current owner of the NFT
+    ///
+    /// * `to`: [`Address`], The new owner
+    ///
+    /// * `token_id`: [`TokenId`], The NFT to transfer
+    ///
+    /// * `now`: [`i64`], The block production time of the transfer
+    pub fn _transfer(&mut self, from: Address, to: Address, token_id: TokenId, now: i64) {
+        if self.owner_of(token_id) != from {
+            panic!("{}", ERR_INCORRECT_OWNER)
+        } else if self.soulbound.contains(&token_id) {
+            panic!("{}", ERR_SOULBOUND)
+        } else if from != to {
+            self._move(from, to, token_id, now);
+        }
+    }
+
+    /// Mutates the state by moving `token_id` from `from` to `to`, clearing its
+    /// approval and lock. Performs no checks; see {_transfer}.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `from`: [`Address`], The current owner of the NFT
+    ///
+    /// * `to`: [`Address`], The new owner
+    ///
+    /// * `token_id`: [`TokenId`], The NFT to move
+    ///
+    /// * `now`: [`i64`], The block production time of the transfer
+    fn _move(&mut self, from: Address, to: Address, token_id: TokenId, now: i64) {
+        // clear approvals and any lock from the previous owner
+        if self.token_approvals.contains_key(&token_id) {
+            self.approvals_cleared += 1;
+        }
+        self._approve(None, token_id);
+        self.locked.remove(&token_id);
+        self.owners.insert(token_id, to);
+        self._decrease_balance(from);
+        self._increase_balance(to);
+        self._log_transfer(token_id, TransferRecord { from, to, time: now });
+    }
+
+    /// Get the details needed to undo a transfer of `token_id` from `from` to `to`,
+    /// taken before the transfer clears its approval and lock.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `from`: [`Address`], The current owner of the NFT
+    ///
+    /// * `to`: [`Address`], The new owner
+    ///
+    /// * `token_id`: [`TokenId`], The NFT about to be transferred
+    ///
+    /// ### Returns:
+    ///
+    /// The [`PendingTransfer`] to pass to {transfer_product_result}.
+    fn pending_transfer(&self, from: Address, to: Address, token_id: TokenId) -> PendingTransfer {
+        PendingTransfer {
+            from,
+            to,
+            token_id,
+            approved: self.get_approved(token_id),
+            locker: self.locked.get(&token_id).copied(),
+        }
+    }
+
+    /// Mutates the state by moving a token back to its owner before `transfer`, with its
+    /// approval and lock restored. Nothing is changed if the token has left `transfer.to`
+    /// since, e.g. by another transfer or a burn.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `transfer`: [`PendingTransfer`], The transfer to undo
+    ///
+    /// * `now`: [`i64`], The block production time of the undo
+    fn _undo_transfer(&mut self, transfer: PendingTransfer, now: i64) {
+        if self.owners.get(&transfer.token_id) != Some(&transfer.to) {
+            return;
+        }
+        self._move(transfer.to, transfer.from, transfer.token_id, now);
+        self._approve(transfer.approved, transfer.token_id);
+        if let Some(locker) = transfer.locker {
+            self.locked.insert(transfer.token_id, locker);
+        }
+    }
+
+    /// Mutates the state by allocating the nonce for the next product call to the user contract.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] nonce greater than every nonce allocated before.
+    fn _next_user_nonce(&mut self) -> u128 {
+        self.user_contract_nonce += 1;
+        self.user_contract_nonce
+    }
+
+    /// Mutates the state by building the `mint_product` calls for the tokens of a
+    /// {batch_mint}, with the `batch_mint_result` callback attached.
+    /// Tokens of the range that no longer exist are skipped.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `contract_address`: [`Address`] The address of this contract.
+    ///
+    /// * `from`: [`u128`] The `total_count` before the batch, exclusive.
+    ///
+    /// * `to`: [`u128`] The last minted id, inclusive.
+    ///
+    /// * `payer`: [`Address`] The address that paid the mint fee.
+    ///
+    /// * `fee`: [`u128`] The mint fee held by the contract, 0 if none was charged.
+    ///
+    /// ### Returns:
+    ///
+    /// The [`EventGroup`] registering the products on the user contract.
+    fn _batch_mint_events(
+        &mut self,
+        contract_address: Address,
+        from: u128,
+        to: u128,
+        payer: Address,
+        fee: u128,
+    ) -> EventGroup {
+        let mut event_group = EventGroup::builder();
+        for id in (from + 1)..=to {
+            let token_id = TokenId::from(id);
+            let (owner, token_uri) =
+                match (self.owners.get(&token_id), self.token_uri_details.get(&token_id)) {
+                    (Some(owner), Some(token_uri)) => (*owner, token_uri.clone()),
+                    _ => continue,
+                };
+            event_group
+                .call(self.user_contract_accress, mint_product(self))
+                .argument(owner)
+                .argument(contract_address)
+                .argument(token_id)
+                .argument(token_uri.status)
+                .argument(token_uri.mpg_time)
+                .argument(token_uri.exp_time)
+                .argument(String::new())
+                .argument(self._next_user_nonce())
+                .done();
+        }
+        event_group
+            .with_callback(SHORTNAME_BATCH_MINT_RESULT)
+            .argument(from)
+            .argument(to)
+            .argument(payer)
+            .argument(fee)
+            .done();
+        event_group.build()
+    }
+
+    /// Mutates the state by appending a transfer to the history of `token_id`,
+    /// dropping the oldest entries beyond `transfer_log_limit`.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`], The transferred NFT
+    ///
+    /// * `record`: [`TransferRecord`], The transfer to log
+    fn _log_transfer(&mut self, token_id: TokenId, record: TransferRecord) {
+        let limit = self.transfer_log_limit as usize;
+        if limit == 0 {
+            return;
+        }
+        let mut log = self.transfer_log.get(&token_id).cloned().unwrap_or_default();
+        log.push(record);
+        if log.len() > limit {
+            log.drain(..log.len() - limit);
+        }
+        self.transfer_log.insert(token_id, log);
+    }
+
+    /// Mutates the state by adding one token to the balance of `owner`.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `owner`: [`Address`], The owner receiving a token
+    fn _increase_balance(&mut self, owner: Address) {
+        let balance = self.balance_of(owner);
+        self.balances.insert(owner, balance + 1);
+    }
+
+    /// Mutates the state by removing one token from the balance of `owner`.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `owner`: [`Address`], The owner losing a token
+    fn _decrease_balance(&mut self, owner: Address) {
+        let balance = self.balance_of(owner);
+        if balance <= 1 {
+            self.balances.remove(&owner);
+        } else {
+            self.balances.insert(owner, balance - 1);
+        }
+    }
+
+    /// Mutates the state by recomputing `balances` from `owners`.
+    fn _rebuild_balances(&mut self) {
+        let owners: Vec<Address> = self.owners.iter().map(|(_, owner)| *owner).collect();
+        self.balances = SortedVecMap::new();
+        for owner in owners {
+            self._increase_balance(owner);
+        }
+    }
+
+    /// Helper function checking that minting `count` tokens to `to` respects the per-wallet limit.
+    /// Throws if the limit would be exceeded.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `to`: [`Address`] The recipient of the mint.
+    ///
+    /// * `count`: [`u128`] The number of tokens to be minted.
+    pub fn check_mint_limit(&self, to: Address, count: u128) {
+        if let Some(max_per_wallet) = self.max_per_wallet {
+            if self.balance_of(to).saturating_add(count) > max_per_wallet {
+                panic!("{}", ERR_WALLET_MINT_LIMIT)
+            }
+        }
+    }
+
+    /// Mutates the state by counting `count` tokens towards the per-block mint limit.
+    /// The count restarts whenever the block time advances.
+    /// Throws if the limit would be exceeded.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `count`: [`u128`] The number of tokens to be minted.
+    ///
+    /// * `block_time`: [`i64`] The block time of the mint.
+    fn _record_block_mints(&mut self, count: u128, block_time: i64) {
+        if block_time != self.last_mint_block {
+            self.last_mint_block = block_time;
+            self.mints_this_block = 0;
+        }
+        let minted = self.mints_this_block.saturating_add(count);
+        if let Some(max_mints_per_block) = self.max_mints_per_block {
+            if minted > max_mints_per_block {
+                panic!("{}", ERR_BLOCK_MINT_LIMIT)
+            }
+        }
+        self.mints_this_block = minted;
+    }
+
+    /// Check whether `batch_mint` would accept minting `count` tokens to `to`,
+    /// without changing the state. Does not check the caller.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `count`: [`u128`] The number of tokens to be minted.
+    ///
+    /// * `to`: [`Address`] The recipient of the mint.
+    ///
+    /// ### Returns:
+    ///
+    /// Ok if the mint is valid, otherwise an error describing the first failed check.
+    pub fn validate_batch_mint(&self, count: u128, to: Address) -> Result<(), String> {
+        if to == ZERO_ADDRESS {
+            return Err(ERR_MINT_TO_ZERO_ADDRESS.to_string());
+        }
+        if count == 0 {
+            return Err(ERR_MINT_COUNT_ZERO.to_string());
+        }
+        if self.total_count.checked_add(count).is_none() {
+            return Err(ERR_TOKEN_ID_OVERFLOW.to_string());
+        }
+        if let Some(max_per_wallet) = self.max_per_wallet {
+            if self.balance_of(to).saturating_add(count) > max_per_wallet {
+                return Err(ERR_WALLET_MINT_LIMIT.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    /// Helper function resolving the expiry time for a new token.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `exp_time`: [`String`] The expiry time given to the mint.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`String`] `exp_time` itself, or the collection default if it is empty.
+    pub fn exp_time_or_default(&self, exp_time: String) -> String {
+        if exp_time.is_empty() {
+            self.default_exp_time.clone()
+        } else {
+            exp_time
+        }
+    }
+
+    /// Mutates the state by minting `token_id` to `to` with the given metadata.
+    /// As opposed to {mint}, this imposes no restrictions on `ctx.sender`.
+    ///
+    /// Throws if `token_id` already exists, if a metadata field is longer
+    /// than [`MAX_METADATA_LEN`] bytes, or if a metadata time is not a unix timestamp.
+    /// The metadata is recorded with the current [`METADATA_SCHEMA_VERSION`].
+    ///
+    /// ### Parameters:
+    ///
+    /// * `to`: [`Address`], The owner of the minted token
+    ///
+    /// * `token_id`: [`TokenId`], The id of the minted token
+    ///
+    /// * `metadata`: [`UriMetadata`], The metadata of the minted token
+    ///
+    /// * `now`: [`i64`], The block production time of the mint
+    pub fn _mint(&mut self, to: Address, token_id: TokenId, metadata: UriMetadata, now: i64) {
+        if self.exists(token_id) {
+            panic!("{}", ERR_TOKEN_EXISTS)
+        }
+        if self.quantities.contains_key(&token_id) {
+            panic!("{}", ERR_FUNGIBLE)
+        }
+        let metadata = validated_metadata(to, metadata);
+        self.parsed_times.insert(token_id, UriMetadataParsed::parse(&metadata));
+        self.owners.insert(token_id, to);
+        self.token_uri_details.insert(token_id, metadata);
+        self.metadata_versions.insert(token_id, METADATA_SCHEMA_VERSION);
+        self.minted_at.insert(token_id, now);
+        self._increase_balance(to);
+    }
+
+    /// Mutates the state by burning `token_id`, clearing its owner, approval and metadata.
+    /// As opposed to {burn}, this imposes no restrictions on `ctx.sender`.
+    /// The token id stays allocated in `total_count`.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`], The token to burn
+    pub fn _burn(&mut self, token_id: TokenId) {
+        self._approve(None, token_id);
+        if let Some(owner) = self.owners.remove(&token_id) {
+            self._decrease_balance(owner);
+        }
+        self.token_uri_details.remove(&token_id);
+        self.metadata_versions.remove(&token_id);
+        self.parsed_times.remove(&token_id);
+        self.minted_at.remove(&token_id);
+        self.soulbound.remove(&token_id);
+        self.locked.remove(&token_id);
+        self.frozen_metadata.remove(&token_id);
+        self.imported_from.remove(&token_id);
+    }
+
+    /// Mutates the state by burning `token_id` and returning everything stored for it,
+    /// so that it can be put back by {_restore_record}. Throws if token_id does not exist.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`], The token to take out of the ledger
+    ///
+    /// ### Returns:
+    ///
+    /// The [`TokenRecord`] of the token before it was burned.
+    fn _take_record(&mut self, token_id: TokenId) -> TokenRecord {
+        let owner = self.owner_of(token_id);
+        let metadata = match self.metadata_of(token_id) {
+            None => panic!("{}", ERR_EXPORT_NO_METADATA),
+            Some(metadata) => metadata,
+        };
+        let record = TokenRecord {
+            owner,
+            metadata,
+            schema_version: self
+                .metadata_schema_version(token_id)
+                .unwrap_or(METADATA_SCHEMA_VERSION),
+            minted_at: self.minted_at(token_id).unwrap_or(0),
+            approved: self.get_approved(token_id),
+            soulbound: self.soulbound.contains(&token_id),
+            frozen: self.frozen_metadata.contains(&token_id),
+            locker: self.locked.get(&token_id).copied(),
+            imported_from: self.imported_from.get(&token_id).copied(),
+        };
+        self._burn(token_id);
+        record
+    }
+
+    /// Mutates the state by putting back a token taken out by {_take_record}, exactly as it was.
+    /// As opposed to {_mint}, no mint checks are applied and the original mint time is kept.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `token_id`: [`TokenId`], The token to restore
+    ///
+    /// * `record`: [`TokenRecord`], Everything stored for the token before it was taken out
+    fn _restore_record(&mut self, token_id: TokenId, record: TokenRecord) {
+        self.parsed_times.insert(token_id, UriMetadataParsed::parse(&record.metadata));
+        self.owners.insert(token_id, record.owner);
+        self.token_uri_details.insert(token_id, record.metadata);
+        self.metadata_versions.insert(token_id, record.schema_version);
+        self.minted_at.insert(token_id, record.minted_at);
+        self._increase_balance(record.owner);
+        if let Some(approved) = record.approved {
+            self.token_approvals.insert(token_id, approved);
+        }
+        if record.soulbound {
+            self.soulbound.insert(token_id);
+        }
+        if record.frozen {
+            self.frozen_metadata.insert(token_id);
+        }
+        if let Some(locker) = record.locker {
+            self.locked.insert(token_id, locker);
+        }
+        if let Some(origin) = record.imported_from {
+            self.imported_from.insert(token_id, origin);
+        }
+    }
+
+    /// Check the relationships between the mappings of the state, for debugging state drift.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`Vec<String>`] describing every violation found, empty when the state is consistent.
+    pub fn verify_invariants(&self) -> Vec<String> {
+        let mut violations = vec![];
+        for (token_id, _) in self.token_approvals.iter() {
+            if !self.exists(*token_id) {
+                violations.push(format!(
+                    "approval for nonexistent token {}",
+                    token_id.into_inner()
+                ));
+            }
+        }
+        for (token_id, _) in self.owners.iter() {
+            if !self.token_uri_details.contains_key(token_id) {
+                violations.push(format!("token {} has no metadata", token_id.into_inner()));
+            }
+            if token_id.into_inner() > self.total_count {
+                violations.push(format!("token {} is above total_count", token_id.into_inner()));
+            }
+        }
+        for (token_id, _) in self.token_uri_details.iter() {
+            if !self.exists(*token_id) && !self.quantities.contains_key(token_id) {
+                violations.push(format!(
+                    "metadata for nonexistent token {}",
+                    token_id.into_inner()
+                ));
+            }
+        }
+
+        let mut counted: SortedVecMap<Address, u128> = SortedVecMap::new();
+        for (_, owner) in self.owners.iter() {
+            let count = counted.get(owner).copied().unwrap_or(0);
+            counted.insert(*owner, count + 1);
+        }
+        for (owner, count) in counted.iter() {
+            if self.balance_of(*owner) != *count {
+                violations.push(format!(
+                    "balance of {:?} is {} but owns {} tokens",
+                    owner,
+                    self.balance_of(*owner),
+                    count
+                ));
+            }
+        }
+        for (owner, balance) in self.balances.iter() {
+            if !counted.contains_key(owner) {
+                violations.push(format!(
+                    "balance of {:?} is {} but owns no tokens",
+                    owner, balance
+                ));
+            }
+        }
+        violations
+    }
+}
+
+/// Throws unless `sender` is the owner of the contract.
+/// Used by every action restricted to the contract owner.
+///
+/// ### Parameters:
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `sender`: [`Address`], the caller of the action.
+fn assert_owner(state: &NFTContractState, sender: Address) {
+    if !state.is_contract_owner(sender) {
+        panic!("{}", ERR_NOT_OWNER)
+    }
+}
+
+/// Initial function to bootstrap the contracts state.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], initial context.
+///
+/// * `name`: [`String`], A descriptive name for a collection of NFTs in this contract.
+///
+/// * `symbol`: [`String`], An abbreviated name for NFTs in this contract.
+///
+/// * `uri_template`: [`String`], Template for uri´s associated with NFTs in this contract.
+///
+/// * `mint_fee`: [`u128`], Fee per token charged by `batch_mint`, 0 for free minting.
+///
+/// * `fee_token`: [`Address`], MPC-20 token contract the mint fee is paid in.
+///
+/// * `default_exp_time`: [`String`], Expiry time used when a mint is given an empty `exp_time`.
+///
+/// * `max_per_wallet`: [`Option<u128>`], Maximum tokens a wallet may be minted, none for no limit.
+///
+/// * `transfer_log_limit`: [`u32`], Number of transfers kept in the history of each token.
+///
+/// * `transfer_mode`: [`u8`], 0 if only the contract owner may transfer tokens, 1 for standard approvals.
+///
+/// * `require_placeholder`: [`bool`], True to reject a non-empty `uri_template` without `{id}`.
+///
+/// * `mint_shortname`: [`u32`], Shortname of `mint_product` on the user contract, 0x03 in this repository.
+///
+/// * `transfer_shortname`: [`u32`], Shortname of `transfer_product` on the user contract, 0x02 in this repository.
+///
+/// ### Returns:
+///
+/// The new state object of type [`NFTContractState`].
+#[init]
+pub fn initialize(
+    ctx: ContractContext,
+    name: String,
+    symbol: String,
+    user_contract_address_: Address,
+    uri_template: String,
+    mint_fee: u128,
+    fee_token: Address,
+    default_exp_time: String,
+    max_per_wallet: Option<u128>,
+    transfer_log_limit: u32,
+    transfer_mode: u8,
+    require_placeholder: bool,
+    mint_shortname: u32,
+    transfer_shortname: u32,
+) -> NFTContractState {
+    if transfer_mode != TRANSFER_MODE_CUSTODIAL && transfer_mode != TRANSFER_MODE_STANDARD {
+        panic!("{}", ERR_UNKNOWN_TRANSFER_MODE)
+    }
+    if require_placeholder && !uri_template.is_empty() && !uri_template.contains("{id}") {
+        panic!("{}", ERR_MISSING_PLACEHOLDER)
+    }
+    parse_time(&default_exp_time);
+    NFTContractState {
+        name,
+        symbol,
+        user_contract_accress: user_contract_address_,
+        owners: SortedVecMap::new(),
+        token_approvals: SortedVecMap::new(),
+        operator_approvals: SortedVecMap::new(),
+        uri_template,
+        token_uri_details: SortedVecMap::new(),
+        metadata_versions: SortedVecMap::new(),
+        contract_owner: ctx.sender,
+        total_count: 0,
+        mint_fee,
+        fee_token,
+        default_exp_time,
+        minted_at: SortedVecMap::new(),
+        balances: SortedVecMap::new(),
+        state_version: 1,
+        max_per_wallet,
+        mint_delegation: None,
+        transfer_log: SortedVecMap::new(),
+        transfer_log_limit,
+        parsed_times: SortedVecMap::new(),
+        soulbound: SortedVec::new(),
+        locked: SortedVecMap::new(),
+        event_listener: None,
+        frozen_metadata: SortedVec::new(),
+        metadata_frozen_all: false,
+        max_mints_per_block: None,
+        last_mint_block: 0,
+        mints_this_block: 0,
+        reserved: SortedVec::new(),
+        transfer_mode,
+        approvals_cleared: 0,
+        user_contract_nonce: 0,
+        quantities: SortedVecMap::new(),
+        fungible_balances: SortedVecMap::new(),
+        scoped_approvals: SortedVecMap::new(),
+        import_origins: SortedVec::new(),
+        imported_from: SortedVecMap::new(),
+        allowlist: SortedVec::new(),
+        mint_shortname,
+        transfer_shortname,
+        grace_period_seconds: 0,
+    }
+}
+
+/// Change or reaffirm the approved address for an NFT.
+/// None indicates there is no approved address.
+/// Throws unless `ctx.sender` is the current NFT owner, or an authorized
+/// operator of the current owner.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `approved`: [`Option<Address>`], The new approved NFT controller.
+///
+/// * `token_id`: [`TokenId`], The NFT to approve.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x05)]
+pub fn approve(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    approved: Option<Address>,
+    token_id: TokenId,
+) -> NFTContractState {
+    if !state.exists(token_id) {
+        panic!("{}", ERR_APPROVE_NONEXISTENT)
+    }
+    let owner = state.owner_of(token_id);
+    if ctx.sender != owner
+        && !state.is_approved_for_all(owner, ctx.sender, unix_seconds(ctx.block_production_time))
+    {
+        panic!("{}", ERR_APPROVE_UNAUTHORIZED)
+    }
+    state._approve(approved, token_id);
+    state
+}
+
+/// Change or reaffirm the approved address for several NFTs in one call.
+/// None indicates there is no approved address.
+/// Throws unless `ctx.sender` is the current owner, or an authorized operator
+/// of the current owner, of every listed NFT; in that case no approval is changed.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `approved`: [`Option<Address>`], The new approved NFT controller.
+///
+/// * `token_ids`: [`Vec<TokenId>`], The NFTs to approve.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x14)]
+pub fn approve_range(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    approved: Option<Address>,
+    token_ids: Vec<TokenId>,
+) -> NFTContractState {
+    let now = unix_seconds(ctx.block_production_time);
+    for token_id in token_ids.iter().copied() {
+        if !state.exists(token_id) {
+            panic!("{}", ERR_APPROVE_NONEXISTENT)
+        }
+        let owner = state.owner_of(token_id);
+        if ctx.sender != owner
+            && !state.is_approved_for_all(owner, ctx.sender, now)
+        {
+            panic!("{}", ERR_APPROVE_UNAUTHORIZED)
+        }
+    }
+    for token_id in token_ids {
+        state._approve(approved, token_id);
+    }
+    state
+}
+
+/// Clear the approved address of several NFTs in one call.
+/// NFTs that do not exist, or of which `ctx.sender` is neither the owner nor an
+/// authorized operator of the owner, are skipped.
+///
+/// If an event listener is set, it is notified with `approvals_revoked`, carrying
+/// the number of approvals that were actually cleared.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `token_ids`: [`Vec<TokenId>`], The NFTs to clear the approval of.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the event listener, if one is set.
+#[action(shortname = 0x2B)]
+pub fn revoke_approvals(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    token_ids: Vec<TokenId>,
+) -> (NFTContractState, Vec<EventGroup>) {
+    let mut cleared: u128 = 0;
+    let now = unix_seconds(ctx.block_production_time);
+    for token_id in token_ids {
+        if !state.exists(token_id) {
+            continue;
+        }
+        let owner = state.owner_of(token_id);
+        if ctx.sender != owner
+            && !state.is_approved_for_all(owner, ctx.sender, now)
+        {
+            continue;
+        }
+        if state.token_approvals.contains_key(&token_id) {
+            state._approve(None, token_id);
+            cleared += 1;
+        }
+    }
+
+    let mut events = vec![];
+    if let Some(event_listener) = state.event_listener {
+        let mut event_group = EventGroup::builder();
+        event_group
+            .call(event_listener, approvals_revoked())
+            .argument(ctx.sender)
+            .argument(cleared)
+            .done();
+        events.push(event_group.build());
+    }
+
+    (state, events)
+}
+
+/// Enable or disable approval for a third party (operator) to manage all of
+/// `ctx.sender`'s assets. Throws if `operator` == `ctx.sender`.
+///
+/// If an event listener is set, it is notified with `approval_for_all`, or with
+/// `approval_for_all_noop` when revoking an approval that did not exist.
+///
+/// ### Parameters:
+///
+/// * `context`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `operator`: [`Address`], Address to add to the set of authorized operators.
+///
+/// * `approved`: [`bool`], True if the operator is approved, false to revoke approval.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the event listener of the change, if one is set.
+#[action(shortname = 0x07)]
+pub fn set_approval_for_all(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    operator: Address,
+    approved: bool,
+) -> (NFTContractState, Vec<EventGroup>) {
+    if operator == ctx.sender {
+        panic!("{}", ERR_APPROVE_TO_CALLER)
+    }
+    let existed = state._set_approval_for_all(ctx.sender, operator, approved, 0);
+
+    let mut events = vec![];
+    if let Some(event_listener) = state.event_listener {
+        let mut event_group = EventGroup::builder();
+        if approved || existed {
+            event_group
+                .call(event_listener, approval_for_all())
+                .argument(ctx.sender)
+                .argument(operator)
+                .argument(approved)
+                .argument(state.balance_of(ctx.sender))
+                .done();
+        } else {
+            event_group
+                .call(event_listener, approval_for_all_noop())
+                .argument(ctx.sender)
+                .argument(operator)
+                .done();
+        }
+        events.push(event_group.build());
+    }
+
+    (state, events)
+}
+
+/// Enable or disable approval for several operators to manage all of `ctx.sender`'s
+/// assets in one call. Throws if any operator == `ctx.sender`, in which case no
+/// approval is changed.
+///
+/// If an event listener is set, it is notified once with `approval_for_all_many`.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `operators`: [`Vec<Address>`], Addresses to add to or remove from the set of authorized operators.
+///
+/// * `approved`: [`bool`], True if the operators are approved, false to revoke approval.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the event listener of the change, if one is set.
+#[action(shortname = 0x32)]
+pub fn set_approval_for_all_many(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    operators: Vec<Address>,
+    approved: bool,
+) -> (NFTContractState, Vec<EventGroup>) {
+    if operators.contains(&ctx.sender) {
+        panic!("{}", ERR_APPROVE_TO_CALLER)
+    }
+    for operator in operators.iter() {
+        state._set_approval_for_all(ctx.sender, *operator, approved, 0);
+    }
+
+    let mut events = vec![];
+    if let Some(event_listener) = state.event_listener {
+        let mut event_group = EventGroup::builder();
+        event_group
+            .call(event_listener, approval_for_all_many())
+            .argument(ctx.sender)
+            .argument(operators)
+            .argument(approved)
+            .argument(state.balance_of(ctx.sender))
+            .done();
+        events.push(event_group.build());
+    }
+
+    (state, events)
+}
+
+/// Approve a third party (operator) to manage only those of `ctx.sender`'s assets
+/// whose metadata has a given status, or remove such a scoped approval.
+/// Throws if `operator` == `ctx.sender`.
+///
+/// An operator has at most one scope per owner; a new scope replaces the previous one.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `operator`: [`Address`], Address to grant the scoped approval to.
+///
+/// * `status`: [`Option<String>`], The status of the tokens `operator` may manage, or none to revoke.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x2C)]
+pub fn set_scoped_approval(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    operator: Address,
+    status: Option<String>,
+) -> NFTContractState {
+    if operator == ctx.sender {
+        panic!("{}", ERR_APPROVE_TO_CALLER)
+    }
+    let scope = OperatorScope {
+        owner: ctx.sender,
+        operator,
+    };
+    match status {
+        Some(status) => {
+            state.scoped_approvals.insert(scope, status);
+        }
+        None => {
+            state.scoped_approvals.remove(&scope);
+        }
+    }
+    state
+}
+
+/// Approve a third party (operator) to manage all of `ctx.sender`'s assets
+/// until a given time in unix seconds. Throws if `operator` == `ctx.sender`.
+///
+/// An `expires_at` of 0 grants an approval that never expires, like {set_approval_for_all}.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `operator`: [`Address`], Address to add to the set of authorized operators.
+///
+/// * `expires_at`: [`i64`], Unix time in seconds after which the approval lapses.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x09)]
+pub fn set_approval_for_all_until(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    operator: Address,
+    expires_at: i64,
+) -> NFTContractState {
+    if operator == ctx.sender {
+        panic!("{}", ERR_APPROVE_TO_CALLER)
+    }
+    state._set_approval_for_all(ctx.sender, operator, true, expires_at);
+    state
+}
+
+/// Revoke every operator approval granted by `ctx.sender`, including scoped approvals.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x0A)]
+pub fn revoke_all_approvals(ctx: ContractContext, mut state: NFTContractState) -> NFTContractState {
+    let granted: Vec<OperatorScope> = state
+        .operator_approvals
+        .iter()
+        .filter(|(scope, _)| scope.owner == ctx.sender)
+        .map(|(scope, _)| *scope)
+        .collect();
+    for scope in granted {
+        state.operator_approvals.remove(&scope);
+    }
+    let scopes: Vec<OperatorScope> = state
+        .scoped_approvals
+        .iter()
+        .filter(|(scope, _)| scope.owner == ctx.sender)
+        .map(|(scope, _)| *scope)
+        .collect();
+    for scope in scopes {
+        state.scoped_approvals.remove(&scope);
+    }
+    state
+}
+
+/// Revoke an operator approval on behalf of its owner, e.g. after the operator key was compromised.
+/// Removes both the full and the scoped approval of the pair.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `operator` is an operator of `owner`, or holds a scoped approval from it
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `owner`: [`Address`], The owner who granted the approval.
+///
+/// * `operator`: [`Address`], The operator to revoke.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x0F)]
+pub fn admin_revoke_operator(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    owner: Address,
+    operator: Address,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    let scope = OperatorScope { owner, operator };
+    let scoped = state.scoped_approvals.remove(&scope).is_some();
+    if !scoped && !state.operators_of(owner).contains(&operator) {
+        panic!("{}", ERR_NO_OPERATOR_APPROVAL)
+    }
+    state._set_approval_for_all(owner, operator, false, 0);
+    state
+}
+
+/// Transfer ownership of an NFT.
+///
+/// In the custodial transfer mode, throws unless `ctx.sender` owns the contract.
+/// In the standard transfer mode, throws unless `ctx.sender` is the current owner, an authorized
+/// operator, or the approved address for this NFT. Throws if `from` is
+/// not the current owner. Throws if `token_id` is not a valid NFT.
+/// Throws if `token_id` is locked by an address other than `ctx.sender`.
+/// Throws if `token_id` is expired, see {is_expired}.
+/// A transfer to the current owner changes nothing and is not sent to the user contract.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `from`: [`Address`], The current owner of the NFT
+///
+/// * `to`: [`Address`], The new owner
+///
+/// * `token_id`: [`TokenId`], The NFT to transfer
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the user contract of the transfer.
+#[action(shortname = 0x03)]
+pub fn transfer_from(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    from: Address,
+    to: Address,
+    token_id: TokenId,
+) -> (NFTContractState, Vec<EventGroup>) {
+    let now = unix_seconds(ctx.block_production_time);
+    if state.transfer_mode == TRANSFER_MODE_CUSTODIAL {
+        assert_owner(&state, ctx.sender);
+    }
+    if state.transfer_mode == TRANSFER_MODE_STANDARD
+        && !state.is_approved_or_owner(ctx.sender, token_id, now)
+    {
+        panic!("{}", ERR_TRANSFER_UNAUTHORIZED)
+    } else if state.locked.get(&token_id).map_or(false, |locker| *locker != ctx.sender) {
+        panic!("{}", ERR_LOCKED)
+    } else if state.is_expired(token_id, now) {
+        panic!("{}", ERR_EXPIRED)
+    } else {
+        let pending = state.pending_transfer(from, to, token_id);
+        state._transfer(from, to, token_id, ctx.block_production_time);
+        if from == to {
+            return (state, vec![]);
+        }
+
+        let mut event_group = EventGroup::builder();
+        event_group
+            .call(state.user_contract_accress, transfer_product(&state))
+            .argument(from)
+            .argument(to)
+            .argument(ctx.contract_address)
+            .argument(token_id)
+            .argument(String::new())
+            .argument(state._next_user_nonce())
+            .done();
+        event_group
+            .with_callback(SHORTNAME_TRANSFER_PRODUCT_RESULT)
+            .argument(pending)
+            .done();
+
+        (state, vec![event_group.build()])
+    }
+}
+
+/// Transfer ownership of an NFT on behalf of its owner, for custodial moves.
+///
+/// As opposed to {transfer_from}, no approval is required, but only the
+/// contract owner may call it. Throws if `from` is not the current owner.
+/// A transfer to the current owner changes nothing and is not sent to the user contract.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `from`: [`Address`], The current owner of the NFT
+///
+/// * `to`: [`Address`], The new owner
+///
+/// * `token_id`: [`TokenId`], The NFT to transfer
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the user contract of the transfer.
+#[action(shortname = 0x06)]
+pub fn admin_transfer(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    from: Address,
+    to: Address,
+    token_id: TokenId,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    let pending = state.pending_transfer(from, to, token_id);
+    state._transfer(from, to, token_id, ctx.block_production_time);
+    if from == to {
+        return (state, vec![]);
+    }
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, transfer_product(&state))
+        .argument(from)
+        .argument(to)
+        .argument(ctx.contract_address)
+        .argument(token_id)
+        .argument(String::new())
+        .argument(state._next_user_nonce())
+        .done();
+    event_group
+        .with_callback(SHORTNAME_TRANSFER_PRODUCT_RESULT)
+        .argument(pending)
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Transfer ownership of an NFT as an administrative override, e.g. to resolve a dispute.
+///
+/// As opposed to {admin_transfer}, the transfer ignores soulbound status. Like
+/// {admin_transfer}, it ignores locks. Every call is recorded by a `force_transfer_audit`
+/// event to the event listener carrying the `reason`.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `from` is the current owner of `token_id`
+/// - an event listener is set, so the override cannot go unrecorded
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `from`: [`Address`], The current owner of the NFT
+///
+/// * `to`: [`Address`], The new owner
+///
+/// * `token_id`: [`TokenId`], The NFT to transfer
+///
+/// * `reason`: [`String`], Why the override was necessary
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// an event notifying the user contract of the transfer, and the audit event.
+#[action(shortname = 0x28)]
+pub fn force_transfer(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    from: Address,
+    to: Address,
+    token_id: TokenId,
+    reason: String,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    let event_listener = match state.event_listener {
+        None => panic!("{}", ERR_NO_EVENT_LISTENER),
+        Some(event_listener) => event_listener,
+    };
+    if state.owner_of(token_id) != from {
+        panic!("{}", ERR_INCORRECT_OWNER)
+    }
+    let pending = state.pending_transfer(from, to, token_id);
+    state._move(from, to, token_id, ctx.block_production_time);
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, transfer_product(&state))
+        .argument(from)
+        .argument(to)
+        .argument(ctx.contract_address)
+        .argument(token_id)
+        .argument(reason.clone())
+        .argument(state._next_user_nonce())
+        .done();
+    event_group
+        .with_callback(SHORTNAME_TRANSFER_PRODUCT_RESULT)
+        .argument(pending)
+        .done();
+
+    let mut audit_group = EventGroup::builder();
+    audit_group
+        .call(event_listener, force_transfer_audit())
+        .argument(from)
+        .argument(to)
+        .argument(token_id)
+        .argument(reason)
+        .done();
+
+    (state, vec![event_group.build(), audit_group.build()])
+}
+
+/// Moves a token owned by the contract's own address, e.g. after it was sent
+/// there by mistake, to a new owner.
+///
+/// The contract's address is not registered on the user contract, so the product
+/// never left the previous owner there; it is moved from that owner to `to`.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `token_id` is owned by `ctx.contract_address`
+/// - the transfer of `token_id` to the contract is still in its transfer history
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `token_id`: [`TokenId`], The NFT to reclaim
+///
+/// * `to`: [`Address`], The new owner
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the user contract of the transfer.
+#[action(shortname = 0x1D)]
+pub fn reclaim_to(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    token_id: TokenId,
+    to: Address,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    if state.owner_of(token_id) != ctx.contract_address {
+        panic!("{}", ERR_NOT_OWNED_BY_CONTRACT)
+    }
+    let previous_owner = match state.transfer_history(token_id).last() {
+        Some((from, _, _)) => *from,
+        None => panic!("{}", ERR_NO_PREVIOUS_OWNER),
+    };
+    let pending = state.pending_transfer(ctx.contract_address, to, token_id);
+    state._transfer(ctx.contract_address, to, token_id, ctx.block_production_time);
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, transfer_product(&state))
+        .argument(previous_owner)
+        .argument(to)
+        .argument(ctx.contract_address)
+        .argument(token_id)
+        .argument(String::new())
+        .argument(state._next_user_nonce())
+        .done();
+    event_group
+        .with_callback(SHORTNAME_TRANSFER_PRODUCT_RESULT)
+        .argument(pending)
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Locks `token_id` so that only `ctx.sender` can transfer it, e.g. while it is for sale.
+/// The lock is released by `unlock_token` or when the token is transferred.
+///
+/// Requirements:
+///
+/// - `ctx.sender` is the owner, an authorized operator, or the approved address for `token_id`
+/// - `token_id` is not already locked
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `token_id`: [`TokenId`], The NFT to lock
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x16)]
+pub fn lock_token(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    token_id: TokenId,
+) -> NFTContractState {
+    if !state.is_approved_or_owner(ctx.sender, token_id, unix_seconds(ctx.block_production_time)) {
+        panic!("{}", ERR_LOCK_UNAUTHORIZED)
+    }
+    if state.locked.contains_key(&token_id) {
+        panic!("{}", ERR_LOCKED)
+    }
+    state.locked.insert(token_id, ctx.sender);
+    state
+}
+
+/// Releases the lock on `token_id`.
+///
+/// Requirements:
+///
+/// - `ctx.sender` is the address that locked `token_id`, or owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `token_id`: [`TokenId`], The NFT to unlock
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x17)]
+pub fn unlock_token(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    token_id: TokenId,
+) -> NFTContractState {
+    match state.locked.get(&token_id) {
+        None => panic!("{}", ERR_NOT_LOCKED),
+        Some(locker) if *locker != ctx.sender && !state.is_contract_owner(ctx.sender) => {
+            panic!("{}", ERR_UNLOCK_UNAUTHORIZED)
+        }
+        Some(_) => {}
+    }
+    state.locked.remove(&token_id);
+    state
+}
+
+/// Mints `token_id` and transfers it to an owner.
+///
+/// Requirements:
+///
+/// - `token_id` must not exist
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `to`: [`Address`], the owner of the minted token.
+///
+/// * `token_id`: [`TokenId`], The new id for the minted token.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the user contract of the mint.
+#[action(shortname = 0x01)]
+pub fn mint(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    to: Address,
+    status: String,
+    mpg_time: String,
+    exp_time: String
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    state.check_mint_limit(to, 1);
+    let exp_time = state.exp_time_or_default(exp_time);
+    state.total_count += 1;
+    let token_uri = UriMetadata { 
+        status: status, 
+        mpg_time: mpg_time, 
+        exp_time: exp_time,
+    };
+
+    let token_id = TokenId::from(state.total_count);
+    state._mint(to, token_id, token_uri.clone(), ctx.block_production_time);
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, mint_product(&state))
+        .argument(to)
+        .argument(ctx.contract_address)
+        .argument(token_id)
+        .argument(token_uri.status)
+        .argument(token_uri.mpg_time)
+        .argument(token_uri.exp_time)
+        .argument(String::new())
+        .argument(state._next_user_nonce())
+        .done();
+    event_group
+        .with_callback(SHORTNAME_MINT_CALLBACK)
+        .argument(vec![token_id])
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Mints a new token to an owner and registers it as a product of the owner's user
+/// on the user contract.
+///
+/// The registration is sent as a `mint_product` event with the `mint_callback`
+/// callback attached. If the user contract rejects it, e.g. because `to` is not
+/// a registered wallet, the callback burns the minted token again, so the two
+/// contracts never disagree about the token.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `to`: [`Address`], the owner of the minted token.
+///
+/// * `metadata`: [`UriMetadata`], the metadata of the minted token.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event registering the product on the user contract.
+#[action(shortname = 0x1E)]
+pub fn mint_and_register(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    to: Address,
+    metadata: UriMetadata,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    state.check_mint_limit(to, 1);
+    let token_uri = UriMetadata {
+        exp_time: state.exp_time_or_default(metadata.exp_time),
+        ..metadata
+    };
+    state.total_count += 1;
+
+    let token_id = TokenId::from(state.total_count);
+    state._mint(to, token_id, token_uri.clone(), ctx.block_production_time);
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, mint_product(&state))
+        .argument(to)
+        .argument(ctx.contract_address)
+        .argument(token_id)
+        .argument(token_uri.status)
+        .argument(token_uri.mpg_time)
+        .argument(token_uri.exp_time)
+        .argument(String::new())
+        .argument(state._next_user_nonce())
+        .done();
+    event_group
+        .with_callback(SHORTNAME_MINT_CALLBACK)
+        .argument(vec![token_id])
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Mints a soulbound token to an owner. The token can be burned but never transferred.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `to`: [`Address`], the owner of the minted token.
+///
+/// * `metadata`: [`UriMetadata`], the metadata of the minted token.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the user contract of the mint.
+#[action(shortname = 0x15)]
+pub fn mint_soulbound(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    to: Address,
+    metadata: UriMetadata,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    state.check_mint_limit(to, 1);
+    let token_uri = UriMetadata {
+        exp_time: state.exp_time_or_default(metadata.exp_time),
+        ..metadata
+    };
+    state.total_count += 1;
+
+    let token_id = TokenId::from(state.total_count);
+    state._mint(to, token_id, token_uri.clone(), ctx.block_production_time);
+    state.soulbound.insert(token_id);
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, mint_product(&state))
+        .argument(to)
+        .argument(ctx.contract_address)
+        .argument(token_id)
+        .argument(token_uri.status)
+        .argument(token_uri.mpg_time)
+        .argument(token_uri.exp_time)
+        .argument(String::new())
+        .argument(state._next_user_nonce())
+        .done();
+    event_group
+        .with_callback(SHORTNAME_MINT_CALLBACK)
+        .argument(vec![token_id])
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Mints one new token with identical metadata to each of a list of recipients.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `recipients` is not empty
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `recipients`: [`Vec<Address>`], the owners of the minted tokens, one token each.
+///
+/// * `metadata`: [`UriMetadata`], the metadata of every minted token.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the user contract of every minted token.
+#[action(shortname = 0x1A)]
+pub fn mint_to_many(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    recipients: Vec<Address>,
+    metadata: UriMetadata,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    if recipients.is_empty() {
+        panic!("{}", ERR_NO_RECIPIENTS)
+    }
+    let token_uri = UriMetadata {
+        exp_time: state.exp_time_or_default(metadata.exp_time),
+        ..metadata
+    };
+
+    let mut event_group = EventGroup::builder();
+    let mut token_ids = vec![];
+    for to in recipients {
+        state.check_mint_limit(to, 1);
+        state.total_count += 1;
+        let token_id = TokenId::from(state.total_count);
+        state._mint(to, token_id, token_uri.clone(), ctx.block_production_time);
+        token_ids.push(token_id);
+
+        event_group
+            .call(state.user_contract_accress, mint_product(&state))
+            .argument(to)
+            .argument(ctx.contract_address)
+            .argument(token_id)
+            .argument(token_uri.status.clone())
+            .argument(token_uri.mpg_time.clone())
+            .argument(token_uri.exp_time.clone())
+            .argument(String::new())
+            .argument(state._next_user_nonce())
+            .done();
+    }
+    event_group
+        .with_callback(SHORTNAME_MINT_CALLBACK)
+        .argument(token_ids)
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Mints `count` new tokens with identical metadata and transfers them to an owner.
+///
+/// Token ids are allocated sequentially after the current `total_count`. The minted
+/// range is attached to the callbacks as the payload `(from, to, payer, fee)`,
+/// where `from` is exclusive and `to` is inclusive.
+///
+/// Callable by the contract owner, and by the mint delegate while its delegation is active.
+/// At most `max_mints_per_block` tokens are minted per block, if that limit is set.
+///
+/// When a mint fee is configured and `ctx.sender` is not the contract owner,
+/// `mint_fee * count` is first transferred from `ctx.sender` to this contract in the
+/// fee token. If the caller cannot pay, `batch_mint_fee_result` reverts the mint.
+/// Otherwise the products are registered on the user contract, and `batch_mint_result`
+/// forwards the fee to the contract owner, or refunds it if the mint is reverted.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `to`: [`Address`], the owner of the minted tokens.
+///
+/// * `count`: [`u128`], the number of tokens to mint.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the user contract of every minted token.
+#[action(shortname = 0x02)]
+pub fn batch_mint(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    to: Address,
+    count: u128,
+    status: String,
+    mpg_time: String,
+    exp_time: String
+) -> (NFTContractState, Vec<EventGroup>) {
+    if !state.can_mint(ctx.sender, unix_seconds(ctx.block_production_time)) {
+        panic!("{}", ERR_MINT_UNAUTHORIZED)
+    }
+    if let Err(reason) = state.validate_batch_mint(count, to) {
+        panic!("{}", reason)
+    }
+    state._record_block_mints(count, ctx.block_time);
+    let fee = if state.is_contract_owner(ctx.sender) {
+        0
+    } else {
+        match state.mint_fee.checked_mul(count) {
+            None => panic!("{}", ERR_MINT_FEE_OVERFLOW),
+            Some(fee) => fee,
+        }
+    };
+    let exp_time = state.exp_time_or_default(exp_time);
+    let from = state.total_count;
+    for i in 0..count {
+        state.total_count += 1;
+        let _status = status.clone();
+        let _mpg_time = mpg_time.clone();
+        let _exp_time: String = exp_time.clone();
+
+        let token_uri = UriMetadata { 
+            status: _status,
+            mpg_time: _mpg_time,
+            exp_time: _exp_time,
+        };
+
+        let token_id = TokenId::from(state.total_count);
+        state._mint(to, token_id, token_uri, ctx.block_production_time);
+    }
+    let last = state.total_count;
+
+    if fee == 0 {
+        let event_group = state._batch_mint_events(ctx.contract_address, from, last, ctx.sender, 0);
+        return (state, vec![event_group]);
+    }
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.fee_token, token_transfer_from())
+        .argument(ctx.sender)
+        .argument(ctx.contract_address)
+        .argument(fee)
+        .done();
+    event_group
+        .with_callback(SHORTNAME_BATCH_MINT_FEE_RESULT)
+        .argument(from)
+        .argument(last)
+        .argument(ctx.sender)
+        .argument(fee)
+        .done();
+    (state, vec![event_group.build()])
+}
+
+/// Mints a set of explicitly chosen token ids with identical metadata and transfers them to an owner.
+///
+/// `total_count` is raised to the highest minted id so sequential mints never collide with them.
+///
+/// Requirements:
+///
+/// - `token_ids` is not empty
+/// - none of `token_ids` may exist or be reserved
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `to`: [`Address`], the owner of the minted tokens.
+///
+/// * `token_ids`: [`Vec<TokenId>`], the ids to mint.
+///
+/// * `metadata`: [`UriMetadata`], the metadata given to every minted token.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the user contract of every minted token.
+#[action(shortname = 0x04)]
+pub fn mint_with_ids(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    to: Address,
+    token_ids: Vec<TokenId>,
+    metadata: UriMetadata,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    if token_ids.is_empty() {
+        panic!("{}", ERR_MINT_COUNT_ZERO)
+    }
+    state.check_mint_limit(to, token_ids.len() as u128);
+    let token_uri = UriMetadata {
+        exp_time: state.exp_time_or_default(metadata.exp_time),
+        ..metadata
+    };
+    let mut event_group = EventGroup::builder();
+    for token_id in token_ids.iter().copied() {
+        if state.reserved.contains(&token_id) {
+            panic!("{}", ERR_RESERVED)
+        }
+        state._mint(to, token_id, token_uri.clone(), ctx.block_production_time);
+        if token_id.into_inner() > state.total_count {
+            state.total_count = token_id.into_inner();
+        }
+
+        event_group
+            .call(state.user_contract_accress, mint_product(&state))
+            .argument(to)
+            .argument(ctx.contract_address)
+            .argument(token_id)
+            .argument(token_uri.status.clone())
+            .argument(token_uri.mpg_time.clone())
+            .argument(token_uri.exp_time.clone())
+            .argument(String::new())
+            .argument(state._next_user_nonce())
+            .done();
+    }
+    event_group
+        .with_callback(SHORTNAME_MINT_CALLBACK)
+        .argument(token_ids)
+        .done();
+    (state, vec![event_group.build()])
+}
+
+/// Reserves the next `count` token ids for a later drop, without minting them.
+/// The ids can only be minted with {mint_reserved}.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `count`: [`u128`], the number of ids to reserve.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x25)]
+pub fn reserve(ctx: ContractContext, mut state: NFTContractState, count: u128) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    if state.total_count.checked_add(count).is_none() {
+        panic!("{}", ERR_TOKEN_ID_OVERFLOW)
+    }
+    for _ in 0..count {
+        state.total_count += 1;
+        state.reserved.insert(TokenId::from(state.total_count));
+    }
+    state
+}
+
+/// Mints a reserved token id and transfers it to an owner.
+///
+/// Requirements:
+///
+/// - `token_id` is reserved
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `token_id`: [`TokenId`], the reserved id to mint.
+///
+/// * `to`: [`Address`], the owner of the minted token.
+///
+/// * `metadata`: [`UriMetadata`], the metadata of the minted token.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the user contract of the mint.
+#[action(shortname = 0x26)]
+pub fn mint_reserved(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    token_id: TokenId,
+    to: Address,
+    metadata: UriMetadata,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    if !state.reserved.contains(&token_id) {
+        panic!("{}", ERR_NOT_RESERVED)
+    }
+    state.check_mint_limit(to, 1);
+    let token_uri = UriMetadata {
+        exp_time: state.exp_time_or_default(metadata.exp_time),
+        ..metadata
+    };
+    state.reserved.remove(&token_id);
+    state._mint(to, token_id, token_uri.clone(), ctx.block_production_time);
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, mint_product(&state))
+        .argument(to)
+        .argument(ctx.contract_address)
+        .argument(token_id)
+        .argument(token_uri.status)
+        .argument(token_uri.mpg_time)
+        .argument(token_uri.exp_time)
+        .argument(String::new())
+        .argument(state._next_user_nonce())
+        .done();
+    event_group
+        .with_callback(SHORTNAME_MINT_CALLBACK)
+        .argument(vec![token_id])
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Mints one new token to `ctx.sender` and removes the sender from the allowlist,
+/// so every allowlisted address can mint exactly once.
+///
+/// Requirements:
+///
+/// - `ctx.sender` is on the allowlist
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `metadata`: [`UriMetadata`], the metadata of the minted token.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the user contract of the mint.
+#[action(shortname = 0x2E)]
+pub fn allowlist_mint(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    metadata: UriMetadata,
+) -> (NFTContractState, Vec<EventGroup>) {
+    if !state.allowlist.contains(&ctx.sender) {
+        panic!("{}", ERR_NOT_ALLOWLISTED)
+    }
+    state.check_mint_limit(ctx.sender, 1);
+    let token_uri = UriMetadata {
+        exp_time: state.exp_time_or_default(metadata.exp_time),
+        ..metadata
+    };
+    state.allowlist.remove(&ctx.sender);
+    state.total_count += 1;
+
+    let token_id = TokenId::from(state.total_count);
+    state._mint(ctx.sender, token_id, token_uri.clone(), ctx.block_production_time);
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, mint_product(&state))
+        .argument(ctx.sender)
+        .argument(ctx.contract_address)
+        .argument(token_id)
+        .argument(token_uri.status)
+        .argument(token_uri.mpg_time)
+        .argument(token_uri.exp_time)
+        .argument(String::new())
+        .argument(state._next_user_nonce())
+        .done();
+    event_group
+        .with_callback(SHORTNAME_MINT_CALLBACK)
+        .argument(vec![token_id])
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Adds addresses to the allowlist of `allowlist_mint`.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `addresses`: [`Vec<Address>`], the addresses to allow.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x2F)]
+pub fn add_to_allowlist(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    addresses: Vec<Address>,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    for address in addresses {
+        state.allowlist.insert(address);
+    }
+    state
+}
+
+/// Removes addresses from the allowlist of `allowlist_mint`.
+/// Addresses that are not on the allowlist are ignored.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `addresses`: [`Vec<Address>`], the addresses to remove.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x30)]
+pub fn remove_from_allowlist(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    addresses: Vec<Address>,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    for address in addresses {
+        state.allowlist.remove(&address);
+    }
+    state
+}
+
+/// Updates the metadata of an existing token, changing only the fields that are given.
+///
+/// Requirements:
+///
+/// - `token_id` must exist
+/// - `ctx.sender` owns the contract
+/// - the metadata of `token_id` is not frozen
+/// - no updated field is longer than [`MAX_METADATA_LEN`] bytes
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `token_id`: [`TokenId`], the token to update.
+///
+/// * `status`: [`Option<String>`], the new status, or none to keep the current one.
+///
+/// * `mpg_time`: [`Option<String>`], the new manufacturing time, or none to keep the current one.
+///
+/// * `exp_time`: [`Option<String>`], the new expiry time, or none to keep the current one.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x0B)]
+pub fn update_metadata(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    token_id: TokenId,
+    status: Option<String>,
+    mpg_time: Option<String>,
+    exp_time: Option<String>,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    if state.is_frozen(token_id) {
+        panic!("{}", ERR_METADATA_FROZEN)
+    }
+    let metadata = match state.token_uri_details.get_mut(&token_id) {
+        None => panic!("{}", ERR_UPDATE_NONEXISTENT),
+        Some(metadata) => metadata,
+    };
+    if let Some(status) = status {
+        metadata.status = status;
+    }
+    if let Some(mpg_time) = mpg_time {
+        metadata.mpg_time = mpg_time;
+    }
+    if let Some(exp_time) = exp_time {
+        metadata.exp_time = exp_time;
+    }
+    check_metadata_len(metadata);
+    let parsed = UriMetadataParsed::parse(metadata);
+    state.parsed_times.insert(token_id, parsed);
+    state
+}
+
+/// Permanently freezes the metadata of an existing token.
+///
+/// Requirements:
+///
+/// - `token_id` must exist
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `token_id`: [`TokenId`], the token to freeze.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x1F)]
+pub fn freeze_metadata(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    token_id: TokenId,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    if !state.exists(token_id) {
+        panic!("{}", ERR_FREEZE_NONEXISTENT)
+    }
+    state.frozen_metadata.insert(token_id);
+    state
+}
+
+/// Permanently freezes the metadata of every token, including tokens minted later.
+/// This cannot be undone.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x20)]
+pub fn freeze_all_metadata(ctx: ContractContext, mut state: NFTContractState) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    state.metadata_frozen_all = true;
+    state
+}
+
+/// Changes the expiry time given to minted tokens when no `exp_time` is provided.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `value` is empty or a unix timestamp in seconds
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `value`: [`String`], the new default expiry time, empty for none.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x0C)]
+pub fn set_default_exp_time(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    value: String,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    // Every later mint parses the default, so reject a malformed one up front.
+    parse_time(&value);
+    state.default_exp_time = value;
+    state
+}
+
+/// Changes how long after its `exp_time` a token can still be transferred with
+/// {transfer_from}, e.g. to absorb clock skew and allow last-minute redemptions.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `seconds` is not negative
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `seconds`: [`i64`], the new grace period, 0 to expire tokens at `exp_time`.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x31)]
+pub fn set_grace_period(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    seconds: i64,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    if seconds < 0 {
+        panic!("{}", ERR_NEGATIVE_GRACE_PERIOD)
+    }
+    state.grace_period_seconds = seconds;
+    state
+}
+
+/// Changes the descriptive name of the collection.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `name` is not empty
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `name`: [`String`], the new name.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x1B)]
+pub fn set_name(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    name: String,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    if name.is_empty() {
+        panic!("{}", ERR_EMPTY_NAME)
+    }
+    state.name = name;
+    state
+}
+
+/// Changes the abbreviated name of the collection.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `symbol` is not empty
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `symbol`: [`String`], the new symbol.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x1C)]
+pub fn set_symbol(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    symbol: String,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    if symbol.is_empty() {
+        panic!("{}", ERR_EMPTY_SYMBOL)
+    }
+    state.symbol = symbol;
+    state
+}
+
+/// Temporarily allows another address to use `batch_mint`, e.g. for a campaign run by a third party.
+/// Replaces any previous delegation; an `until` in the past revokes it.
+///
+/// If an event listener is set, it is notified with `minting_delegated`.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `delegate`: [`Address`], the address allowed to mint.
+///
+/// * `until`: [`i64`], unix time in seconds after which the delegation lapses.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the event listener of the delegation, if one is set.
+#[action(shortname = 0x13)]
+pub fn delegate_minting(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    delegate: Address,
+    until: i64,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    state.mint_delegation = Some(MintDelegation { delegate, until });
+
+    let mut events = vec![];
+    if let Some(event_listener) = state.event_listener {
+        let mut event_group = EventGroup::builder();
+        event_group
+            .call(event_listener, minting_delegated())
+            .argument(delegate)
+            .argument(until)
+            .done();
+        events.push(event_group.build());
+    }
+
+    (state, events)
+}
+
+/// Changes the maximum number of tokens a wallet may be minted.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `max_per_wallet`: [`Option<u128>`], the new limit, or none to remove it.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x12)]
+pub fn set_max_per_wallet(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    max_per_wallet: Option<u128>,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    state.max_per_wallet = max_per_wallet;
+    state
+}
+
+/// Changes the maximum number of tokens `batch_mint` may mint within one block.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `max_mints_per_block`: [`Option<u128>`], the new limit, or none to remove it.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x21)]
+pub fn set_max_mints_per_block(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    max_mints_per_block: Option<u128>,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    state.max_mints_per_block = max_mints_per_block;
+    state
+}
+
+/// Gives up ownership of the contract by setting the owner to the zero address.
+/// Any mint delegation is revoked as well.
+///
+/// This is irreversible: afterwards every owner-gated action, such as `mint`
+/// and `admin_transfer`, permanently fails.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] without an owner,
+/// and an event notifying the event listener of the change, if one is set.
+#[action(shortname = 0x18)]
+pub fn renounce_ownership(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    let old_owner = state.contract_owner;
+    let had_delegation = state.mint_delegation.is_some();
+    state.contract_owner = ZERO_ADDRESS;
+    state.mint_delegation = None;
+
+    let mut events = vec![];
+    if let Some(event_listener) = state.event_listener {
+        let mut event_group = EventGroup::builder();
+        event_group
+            .call(event_listener, ownership_changed())
+            .argument(old_owner)
+            .argument(state.contract_owner)
+            .done();
+        if had_delegation {
+            event_group
+                .call(event_listener, minting_delegated())
+                .argument(ZERO_ADDRESS)
+                .argument(0i64)
+                .done();
+        }
+        events.push(event_group.build());
+    }
+
+    (state, events)
+}
+
+/// Sets the contract notified of ownership and operator approval changes.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `event_listener`: [`Option<Address>`], the new listener, or none to stop sending events.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x19)]
+pub fn set_event_listener(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    event_listener: Option<Address>,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    state.event_listener = event_listener;
+    state
+}
+
+/// Migrates the state to the next layout version, rebuilding derived indexes
+/// such as `balances` from the authoritative `owners` mapping.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x0E)]
+pub fn migrate(ctx: ContractContext, mut state: NFTContractState) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    state._rebuild_balances();
+    state.state_version += 1;
+    state
+}
+
+/// Callback for the mint events sent to the user contract.
+///
+/// If the user contract failed to record the products, the minted tokens are
+/// burned again, and the user contract is told to drop any product of the group it
+/// did record, so both contracts agree on which tokens exist.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the callback.
+///
+/// * `callback_ctx`: [`CallbackContext`], the result of the mint events.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `token_ids`: [`Vec<TokenId>`], the tokens minted by the originating action.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`], with the mint reverted on failure,
+/// and an event notifying the user contract of the reverted tokens.
+#[callback(shortname = 0x10)]
+pub fn mint_callback(
+    ctx: ContractContext,
+    callback_ctx: CallbackContext,
+    mut state: NFTContractState,
+    token_ids: Vec<TokenId>,
+) -> (NFTContractState, Vec<EventGroup>) {
+    if callback_ctx.success {
+        return (state, vec![]);
+    }
+    for token_id in token_ids.iter() {
+        state._burn(*token_id);
+    }
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, burn_product())
+        .argument(ctx.contract_address)
+        .argument(token_ids)
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Callback for the `transfer_product` event sent by {transfer_from}, {admin_transfer},
+/// {force_transfer} and {reclaim_to}.
+///
+/// If the user contract refused the transfer, e.g. because a wallet is not registered
+/// there, the token is moved back to its previous owner, so both contracts agree on who
+/// holds it.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the callback.
+///
+/// * `callback_ctx`: [`CallbackContext`], the result of the transfer event.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `transfer`: [`PendingTransfer`], the transfer made by the originating action.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`], with the transfer reverted on failure.
+#[callback(shortname = 0x34)]
+pub fn transfer_product_result(
+    ctx: ContractContext,
+    callback_ctx: CallbackContext,
+    mut state: NFTContractState,
+    transfer: PendingTransfer,
+) -> (NFTContractState, Vec<EventGroup>) {
+    if !callback_ctx.success {
+        state._undo_transfer(transfer, ctx.block_production_time);
+    }
+    (state, vec![])
+}
+
+/// Callback for the mint fee transfer of {batch_mint}.
+///
+/// If the fee was paid, the minted tokens are registered on the user contract.
+/// Otherwise they are burned again; nothing was sent to the user contract yet.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the callback.
+///
+/// * `callback_ctx`: [`CallbackContext`], the result of the fee transfer.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `from`: [`u128`], the `total_count` before the batch, exclusive.
+///
+/// * `to`: [`u128`], the last minted id, inclusive.
+///
+/// * `payer`: [`Address`], the address the fee was charged to.
+///
+/// * `fee`: [`u128`], the charged fee, now held by this contract.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`], with the mint reverted on failure,
+/// and an event registering the products on the user contract on success.
+#[callback(shortname = 0x33)]
+pub fn batch_mint_fee_result(
+    ctx: ContractContext,
+    callback_ctx: CallbackContext,
+    mut state: NFTContractState,
+    from: u128,
+    to: u128,
+    payer: Address,
+    fee: u128,
+) -> (NFTContractState, Vec<EventGroup>) {
+    if !callback_ctx.success {
+        for id in (from + 1)..=to {
+            state._burn(TokenId::from(id));
+        }
+        return (state, vec![]);
+    }
+    let event_group = state._batch_mint_events(ctx.contract_address, from, to, payer, fee);
+    (state, vec![event_group])
+}
+
+/// Callback for the mint events sent to the user contract by {batch_mint}.
+///
+/// Carries the range of minted ids and the mint fee held by the contract. If the
+/// user contract recorded the products, the fee is forwarded to the contract owner.
+/// Otherwise the minted tokens are burned again, the user contract is told to drop
+/// any product of the range it did record, and the fee is refunded to the payer.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the callback.
+///
+/// * `callback_ctx`: [`CallbackContext`], the result of the mint events.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `from`: [`u128`], the `total_count` before the batch, exclusive.
+///
+/// * `to`: [`u128`], the last minted id, inclusive.
+///
+/// * `payer`: [`Address`], the address that paid the mint fee.
+///
+/// * `fee`: [`u128`], the mint fee held by the contract, 0 if none was charged.
+///
+/// ### Returns
 ///
-/// * `ctx`: [`ContractContext`], initial context.
+/// The new state object of type [`NFTContractState`], with the mint reverted on failure,
+/// and events settling the fee and notifying the user contract of the reverted tokens.
+#[callback(shortname = 0x11)]
+pub fn batch_mint_result(
+    ctx: ContractContext,
+    callback_ctx: CallbackContext,
+    mut state: NFTContractState,
+    from: u128,
+    to: u128,
+    payer: Address,
+    fee: u128,
+) -> (NFTContractState, Vec<EventGroup>) {
+    if callback_ctx.success {
+        if fee == 0 {
+            return (state, vec![]);
+        }
+        let mut event_group = EventGroup::builder();
+        event_group
+            .call(state.fee_token, token_transfer())
+            .argument(state.contract_owner)
+            .argument(fee)
+            .done();
+        return (state, vec![event_group.build()]);
+    }
+    let token_ids: Vec<TokenId> = ((from + 1)..=to).map(TokenId::from).collect();
+    for token_id in token_ids.iter() {
+        state._burn(*token_id);
+    }
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, burn_product())
+        .argument(ctx.contract_address)
+        .argument(token_ids)
+        .done();
+    if fee > 0 {
+        event_group
+            .call(state.fee_token, token_transfer())
+            .argument(payer)
+            .argument(fee)
+            .done();
+    }
+
+    (state, vec![event_group.build()])
+}
+
+/// Destroys `token_id`.
 ///
-/// * `name`: [`String`], A descriptive name for a collection of NFTs in this contract.
+/// Throws unless `ctx.sender` is the current owner, an authorized
+/// operator, or the approved address for this NFT. Throws if `token_id` is not a valid NFT.
+/// Throws if `token_id` is locked by an address other than `ctx.sender`.
 ///
-/// * `symbol`: [`String`], An abbreviated name for NFTs in this contract.
+/// ### Parameters:
 ///
-/// * `uri_template`: [`String`], Template for uri´s associated with NFTs in this contract.
+/// * `ctx`: [`ContractContext`], the context for the action call.
 ///
-/// ### Returns:
+/// * `state`: [`NFTContractState`], the current state of the contract.
 ///
-/// The new state object of type [`NFTContractState`].
-#[init]
-pub fn initialize(
+/// * `token_id`: [`TokenId`], The NFT to burn
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the user contract of the burn.
+#[action(shortname = 0x08)]
+pub fn burn(
     ctx: ContractContext,
-    name: String,
-    symbol: String,
-    user_contract_address_: Address,
-    uri_template: String,
-) -> NFTContractState {
-    NFTContractState {
-        name,
-        symbol,
-        user_contract_accress: user_contract_address_,
-        owners: SortedVecMap::new(),
-        token_approvals: SortedVecMap::new(),
-        operator_approvals: SortedVec::new(),
-        uri_template,
-        token_uri_details: SortedVecMap::new(),
-        contract_owner: ctx.sender,
-        total_count: 0
+    mut state: NFTContractState,
+    token_id: TokenId,
+) -> (NFTContractState, Vec<EventGroup>) {
+    if !state.is_approved_or_owner(ctx.sender, token_id, unix_seconds(ctx.block_production_time)) {
+        panic!("{}", ERR_BURN_UNAUTHORIZED)
+    } else if state.locked.get(&token_id).map_or(false, |locker| *locker != ctx.sender) {
+        panic!("{}", ERR_LOCKED)
+    } else {
+        state._burn(token_id);
+
+        let mut event_group = EventGroup::builder();
+        event_group
+            .call(state.user_contract_accress, burn_product())
+            .argument(ctx.contract_address)
+            .argument(vec![token_id])
+            .done();
+
+        (state, vec![event_group.build()])
     }
 }
 
-/// Change or reaffirm the approved address for an NFT.
-/// None indicates there is no approved address.
-/// Throws unless `ctx.sender` is the current NFT owner, or an authorized
-/// operator of the current owner.
+/// Destroys a list of tokens in one call, e.g. to clean up an expired production run.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - when `strict` is set, every id in `token_ids` must exist
+/// - no token in `token_ids` is locked by an address other than `ctx.sender`
 ///
 /// ### Parameters:
 ///
@@ -231,71 +3727,116 @@ pub fn initialize(
 ///
 /// * `state`: [`NFTContractState`], the current state of the contract.
 ///
-/// * `approved`: [`Option<Address>`], The new approved NFT controller.
+/// * `token_ids`: [`Vec<TokenId>`], The NFTs to burn
 ///
-/// * `token_id`: [`u128`], The NFT to approve.
+/// * `strict`: [`bool`], True to throw on nonexistent ids, false to skip them.
 ///
 /// ### Returns
 ///
-/// The new state object of type [`NFTContractState`] with an updated ledger.
-#[action(shortname = 0x05)]
-pub fn approve(
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and a single event notifying the user contract of every burned token.
+#[action(shortname = 0x0D)]
+pub fn burn_batch(
     ctx: ContractContext,
     mut state: NFTContractState,
-    approved: Option<Address>,
-    token_id: u128,
-) -> NFTContractState {
-    let owner = state.owner_of(token_id);
-    if ctx.sender != owner && !state.is_approved_for_all(owner, ctx.sender) {
-        panic!("MPC-721: approve caller is not owner nor authorized operator")
+    token_ids: Vec<TokenId>,
+    strict: bool,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    let mut burned = Vec::new();
+    for token_id in token_ids {
+        if state.exists(token_id) {
+            if state.locked.get(&token_id).map_or(false, |locker| *locker != ctx.sender) {
+                panic!("{}", ERR_LOCKED)
+            }
+            state._burn(token_id);
+            burned.push(token_id);
+        } else if strict {
+            panic!("{}", ERR_BURN_NONEXISTENT)
+        }
     }
-    state._approve(approved, token_id);
-    state
+    if burned.is_empty() {
+        return (state, vec![]);
+    }
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, burn_product())
+        .argument(ctx.contract_address)
+        .argument(burned)
+        .done();
+
+    (state, vec![event_group.build()])
 }
 
-/// Enable or disable approval for a third party (operator) to manage all of
-/// `ctx.sender`'s assets. Throws if `operator` == `ctx.sender`.
+/// Burns up to `max` expired tokens in one call, lowest ids first.
+/// Repeat the call until no event is returned to sweep the whole collection.
+/// Semi-fungible tokens, and tokens locked by an address other than `ctx.sender`, are skipped.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
 ///
 /// ### Parameters:
 ///
-/// * `context`: [`ContractContext`], the context for the action call.
+/// * `ctx`: [`ContractContext`], the context for the action call.
 ///
 /// * `state`: [`NFTContractState`], the current state of the contract.
 ///
-/// * `operator`: [`Address`], Address to add to the set of authorized operators.
+/// * `now`: [`i64`], The reference time in unix seconds, see {is_expired}.
 ///
-/// * `approved`: [`bool`], True if the operator is approved, false to revoke approval.
+/// * `max`: [`u128`], The maximum number of tokens to burn.
 ///
 /// ### Returns
 ///
-/// The new state object of type [`NFTContractState`] with an updated ledger.
-#[action(shortname = 0x07)]
-pub fn set_approval_for_all(
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and a single event notifying the user contract of every burned token.
+#[action(shortname = 0x27)]
+pub fn sweep_expired(
     ctx: ContractContext,
     mut state: NFTContractState,
-    operator: Address,
-    approved: bool,
-) -> NFTContractState {
-    if operator == ctx.sender {
-        panic!("MPC-721: approve to caller")
+    now: i64,
+    max: u128,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    let burned: Vec<TokenId> = state
+        .parsed_times
+        .iter()
+        .map(|(token_id, _)| *token_id)
+        .filter(|token_id| state.exists(*token_id) && state.is_expired(*token_id, now))
+        .filter(|token_id| state.locked.get(token_id).map_or(true, |locker| *locker == ctx.sender))
+        .take(usize::try_from(max).unwrap_or(usize::MAX))
+        .collect();
+    if burned.is_empty() {
+        return (state, vec![]);
     }
-    let operator_approval = OperatorApproval {
-        owner: ctx.sender,
-        operator,
-    };
-    if approved {
-        state.operator_approvals.insert(operator_approval);
-    } else {
-        state.operator_approvals.remove(&operator_approval);
+    for token_id in burned.iter().copied() {
+        state._burn(token_id);
     }
-    state
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, burn_product())
+        .argument(ctx.contract_address)
+        .argument(burned)
+        .done();
+
+    (state, vec![event_group.build()])
 }
 
-/// Transfer ownership of an NFT.
+/// Moves a token to another NFT contract, e.g. to migrate a collection.
 ///
-/// Throws unless `ctx.sender` is the current owner, an authorized
-/// operator, or the approved address for this NFT. Throws if `from` is
-/// not the current owner. Throws if `token_id` is not a valid NFT.
+/// The token is burned here and minted on `target_contract` through its
+/// `mint_imported` action, carrying the owner and metadata. The callback
+/// {export_token_result} restores the token here exactly as it was if the import
+/// fails, and otherwise removes the product from the user contract.
+///
+/// Requirements:
+///
+/// - `token_id` must exist
+/// - `ctx.sender` owns the contract
+/// - `token_id` is not soulbound
+/// - `token_id` is not locked by an address other than `ctx.sender`
 ///
 /// ### Parameters:
 ///
@@ -303,46 +3844,176 @@ pub fn set_approval_for_all(
 ///
 /// * `state`: [`NFTContractState`], the current state of the contract.
 ///
-/// * `from`: [`Address`], The current owner of the NFT
+/// * `token_id`: [`TokenId`], The NFT to export
 ///
-/// * `to`: [`Address`], The new owner
+/// * `target_contract`: [`Address`], The NFT contract importing the token
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event importing the token on `target_contract`.
+#[action(shortname = 0x23)]
+pub fn export_token(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    token_id: TokenId,
+    target_contract: Address,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    if state.soulbound.contains(&token_id) {
+        panic!("{}", ERR_SOULBOUND)
+    }
+    if state.locked.get(&token_id).map_or(false, |locker| *locker != ctx.sender) {
+        panic!("{}", ERR_LOCKED)
+    }
+    let record = state._take_record(token_id);
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(target_contract, mint_imported())
+        .argument(record.owner)
+        .argument(record.metadata.clone())
+        .argument(record.schema_version)
+        .argument(ctx.contract_address)
+        .argument(token_id)
+        .done();
+    event_group
+        .with_callback(SHORTNAME_EXPORT_TOKEN_RESULT)
+        .argument(token_id)
+        .argument(record)
+        .done();
+
+    (state, vec![event_group.build()])
+}
+
+/// Callback for the import event sent by {export_token}.
+///
+/// If the target contract failed to import the token, it is restored here exactly
+/// as it was. Otherwise the user contract is told the product is gone.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the callback.
+///
+/// * `callback_ctx`: [`CallbackContext`], the result of the import event.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `token_id`: [`TokenId`], the exported token.
 ///
-/// * `token_id`: [`u128`], The NFT to transfer
+/// * `record`: [`TokenRecord`], everything stored for the token before the export.
 ///
 /// ### Returns
 ///
-/// The new state object of type [`NFTContractState`] with an updated ledger.
-#[action(shortname = 0x03)]
-pub fn transfer_from(
+/// The new state object of type [`NFTContractState`], with the export reverted on failure,
+/// and an event notifying the user contract of the burn on success.
+#[callback(shortname = 0x24)]
+pub fn export_token_result(
     ctx: ContractContext,
+    callback_ctx: CallbackContext,
     mut state: NFTContractState,
-    from: Address,
-    to: Address,
-    token_id: u128,
-) -> NFTContractState {
-    if !state.is_approved_or_owner(ctx.sender, token_id) {
-        panic!("MPC-721: transfer caller is not owner nor approved")
-    } else {
-        state._transfer(from, to, token_id);
+    token_id: TokenId,
+    record: TokenRecord,
+) -> (NFTContractState, Vec<EventGroup>) {
+    if !callback_ctx.success {
+        state._restore_record(token_id, record);
+        return (state, vec![]);
+    }
 
-        let mut event_group = EventGroup::builder();
-        event_group
-            .call(state.user_contract_accress, transfer_product())
-            .argument(from)
-            .argument(to)
-            .argument(ctx.contract_address)
-            .argument(token_id)
-            .done();
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, burn_product())
+        .argument(ctx.contract_address)
+        .argument(vec![token_id])
+        .done();
+
+    (state, vec![event_group.build()])
+}
 
-        state
+/// Mints a token exported from another NFT contract by its `export_token` action,
+/// and records where it came from.
+///
+/// The token gets the next sequential id here, so `origin_token_id` may differ
+/// from the new id; see {origin_of}.
+///
+/// Requirements:
+///
+/// - `ctx.sender` is an allowed import origin and equals `origin_contract`
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `to`: [`Address`], the owner of the imported token.
+///
+/// * `metadata`: [`UriMetadata`], the metadata of the imported token.
+///
+/// * `schema_version`: [`u16`], the metadata layout version in `origin_contract`, kept here.
+///
+/// * `origin_contract`: [`Address`], the contract the token was exported from.
+///
+/// * `origin_token_id`: [`TokenId`], the id of the token in `origin_contract`.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the user contract of the mint.
+#[action(shortname = 0x22)]
+pub fn mint_imported(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    to: Address,
+    metadata: UriMetadata,
+    schema_version: u16,
+    origin_contract: Address,
+    origin_token_id: TokenId,
+) -> (NFTContractState, Vec<EventGroup>) {
+    if !state.import_origins.contains(&ctx.sender) || origin_contract != ctx.sender {
+        panic!("{}", ERR_IMPORT_ORIGIN)
     }
+    state.check_mint_limit(to, 1);
+    let token_uri = UriMetadata {
+        exp_time: state.exp_time_or_default(metadata.exp_time),
+        ..metadata
+    };
+    state.total_count += 1;
+    let token_id = TokenId::from(state.total_count);
+    state._mint(to, token_id, token_uri.clone(), ctx.block_production_time);
+    state.metadata_versions.insert(token_id, schema_version);
+    state.imported_from.insert(
+        token_id,
+        ImportOrigin {
+            contract: origin_contract,
+            token_id: origin_token_id,
+        },
+    );
+
+    let mut event_group = EventGroup::builder();
+    event_group
+        .call(state.user_contract_accress, mint_product(&state))
+        .argument(to)
+        .argument(ctx.contract_address)
+        .argument(token_id)
+        .argument(token_uri.status)
+        .argument(token_uri.mpg_time)
+        .argument(token_uri.exp_time)
+        .argument(String::new())
+        .argument(state._next_user_nonce())
+        .done();
+    event_group
+        .with_callback(SHORTNAME_MINT_CALLBACK)
+        .argument(vec![token_id])
+        .done();
+
+    (state, vec![event_group.build()])
 }
 
-/// Mints `token_id` and transfers it to an owner.
+/// Allows or disallows an NFT contract to import tokens with {mint_imported}.
 ///
 /// Requirements:
 ///
-/// - `token_id` must not exist
 /// - `ctx.sender` owns the contract
 ///
 /// ### Parameters:
@@ -351,100 +4022,164 @@ pub fn transfer_from(
 ///
 /// * `state`: [`NFTContractState`], the current state of the contract.
 ///
-/// * `to`: [`Address`], the owner of the minted token.
+/// * `origin`: [`Address`], the NFT contract to allow or disallow.
 ///
-/// * `token_id`: [`u128`], The new id for the minted token.
+/// * `allowed`: [`bool`], true to allow `origin`, false to remove it.
 ///
 /// ### Returns
 ///
 /// The new state object of type [`NFTContractState`] with an updated ledger.
-#[action(shortname = 0x01)]
-pub fn mint(
+#[action(shortname = 0x2D)]
+pub fn set_import_origin(
     ctx: ContractContext,
     mut state: NFTContractState,
-    to: Address,
-    status: String,
-    mpg_time: String,
-    exp_time: String
+    origin: Address,
+    allowed: bool,
 ) -> NFTContractState {
-    if ctx.sender != state.contract_owner {
-        panic!("MPC-721: mint only callable by the contract owner")
+    assert_owner(&state, ctx.sender);
+    if allowed {
+        state.import_origins.insert(origin);
     } else {
-        state.total_count += 1;
-        let token_uri = UriMetadata { 
-            status: status, 
-            mpg_time: mpg_time, 
-            exp_time: exp_time
-        };
-
-        state.owners.insert(state.total_count, to);
-        state.token_uri_details.insert(state.total_count, token_uri);
-
-        let mut event_group = EventGroup::builder();
-        event_group
-            .call(state.user_contract_accress, mint_product())
-            .argument(to)
-            .argument(ctx.contract_address)
-            .argument(state.total_count)
-            .done();
-        
-        state
+        state.import_origins.remove(&origin);
     }
+    state
 }
 
-#[action(shortname = 0x02)]
-pub fn batch_mint(
+/// Mints `amount` of a semi-fungible token to an owner. The first mint of `token_id`
+/// creates it with the given metadata; later mints increase its quantity and ignore `metadata`.
+///
+/// Semi-fungible tokens are held as quantities instead of by a single owner, so they
+/// are not tracked by `owner_of` and are not reported to the user contract.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `to` is not the zero address
+/// - `token_id` is not a unique NFT
+/// - `amount` is not 0
+/// - on the first mint, no field of `metadata` is longer than [`MAX_METADATA_LEN`] bytes
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `to`: [`Address`], the receiver of the minted quantity.
+///
+/// * `token_id`: [`TokenId`], the semi-fungible token.
+///
+/// * `amount`: [`u128`], the quantity to mint.
+///
+/// * `metadata`: [`UriMetadata`], the metadata of the token, used on its first mint.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x29)]
+pub fn mint_fungible(
     ctx: ContractContext,
     mut state: NFTContractState,
     to: Address,
-    count: u128,
-    status: String,
-    mpg_time: String,
-    exp_time: String
+    token_id: TokenId,
+    amount: u128,
+    metadata: UriMetadata,
 ) -> NFTContractState {
-    if ctx.sender != state.contract_owner {
-        panic!("MPC-721: mint only callable by the contract owner")
-    } else {
-        for i in 0..count {
-            state.total_count += 1;
-            let _status = status.clone();
-            let _mpg_time = mpg_time.clone();
-            let _exp_time: String = exp_time.clone();
-
-            let token_uri = UriMetadata { 
-                status: _status,
-                mpg_time: _mpg_time,
-                exp_time: _exp_time
+    assert_owner(&state, ctx.sender);
+    if state.exists(token_id) || state.reserved.contains(&token_id) {
+        panic!("{}", ERR_NOT_FUNGIBLE)
+    }
+    if amount == 0 {
+        panic!("{}", ERR_MINT_AMOUNT_ZERO)
+    }
+    if to == ZERO_ADDRESS {
+        panic!("{}", ERR_MINT_TO_ZERO_ADDRESS)
+    }
+    let quantity = match state.quantities.get(&token_id) {
+        Some(quantity) => *quantity,
+        None => {
+            let metadata = UriMetadata {
+                exp_time: state.exp_time_or_default(metadata.exp_time),
+                ..metadata
             };
-
-            state.owners.insert(state.total_count, to);
-            state.token_uri_details.insert(state.total_count, token_uri);
-
-            let mut event_group = EventGroup::builder();
-            event_group
-                .call(state.user_contract_accress, mint_product())
-                .argument(to)
-                .argument(ctx.contract_address)
-                .argument(state.total_count)
-                .done();
+            let metadata = validated_metadata(to, metadata);
+            state.parsed_times.insert(token_id, UriMetadataParsed::parse(&metadata));
+            state.token_uri_details.insert(token_id, metadata);
+            state.metadata_versions.insert(token_id, METADATA_SCHEMA_VERSION);
+            state.minted_at.insert(token_id, ctx.block_production_time);
+            if token_id.into_inner() > state.total_count {
+                state.total_count = token_id.into_inner();
+            }
+            0
         }
-        state
-    }
-}
-
+    };
+    let quantity = match quantity.checked_add(amount) {
+        None => panic!("{}", ERR_QUANTITY_OVERFLOW),
+        Some(quantity) => quantity,
+    };
+    state.quantities.insert(token_id, quantity);
 
+    let holding = FungibleHolding { token_id, holder: to };
+    let balance = state.fungible_balances.get(&holding).copied().unwrap_or(0);
+    state.fungible_balances.insert(holding, balance + amount);
+    state
+}
 
-// #[action(shortname = 0x08)]
-// pub fn burn(ctx: ContractContext, mut state: NFTContractState, token_id: u128) -> NFTContractState {
-//     if !state.is_approved_or_owner(ctx.sender, token_id) {
-//         panic!("MPC-721: burn caller is not owner nor approved")
-//     } else {
-//         let owner = state.owner_of(token_id);
-//         // Clear approvals
-//         state._approve(None, token_id);
+/// Moves part of the quantity of a semi-fungible token to another holder.
+///
+/// Requirements:
+///
+/// - `ctx.sender` is `from` or an authorized operator of `from`
+/// - `token_id` is semi-fungible
+/// - `from` holds at least `amount` of `token_id`
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `from`: [`Address`], the current holder of the quantity.
+///
+/// * `to`: [`Address`], the new holder of the quantity.
+///
+/// * `token_id`: [`TokenId`], the semi-fungible token.
+///
+/// * `amount`: [`u128`], the quantity to move.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x2A)]
+pub fn transfer_amount(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    from: Address,
+    to: Address,
+    token_id: TokenId,
+    amount: u128,
+) -> NFTContractState {
+    if ctx.sender != from
+        && !state.is_approved_for_all(from, ctx.sender, unix_seconds(ctx.block_production_time))
+    {
+        panic!("{}", ERR_TRANSFER_UNAUTHORIZED)
+    }
+    if !state.quantities.contains_key(&token_id) {
+        panic!("{}", ERR_NOT_FUNGIBLE)
+    }
+    let from_holding = FungibleHolding { token_id, holder: from };
+    let from_balance = state.fungible_balances.get(&from_holding).copied().unwrap_or(0);
+    if from_balance < amount {
+        panic!("{}", ERR_AMOUNT_EXCEEDS_BALANCE)
+    }
+    if from_balance == amount {
+        state.fungible_balances.remove(&from_holding);
+    } else {
+        state.fungible_balances.insert(from_holding, from_balance - amount);
+    }
 
-//         state.owners.remove(&token_id);
-//         state.token_uri_details.remove(&token_id);
-//         state
-//     }
-// }
+    let to_holding = FungibleHolding { token_id, holder: to };
+    let to_balance = state.fungible_balances.get(&to_holding).copied().unwrap_or(0);
+    state.fungible_balances.insert(to_holding, to_balance + amount);
+    state
+}
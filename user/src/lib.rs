@@ -5,10 +5,40 @@
 extern crate pbc_contract_codegen;
 
 use create_type_spec_derive::CreateTypeSpec;
-use pbc_contract_common::address::Address;
+use pbc_contract_common::address::{Address, Shortname};
 use pbc_contract_common::context::ContractContext;
+use pbc_contract_common::events::EventGroup;
 use pbc_contract_common::sorted_vec_map::{SortedVec, SortedVecMap};
+use read_write_rpc_derive::ReadWriteRPC;
 use read_write_state_derive::ReadWriteState;
+use std::cmp::Ordering;
+
+/// Maximum number of products returned by a single page of `products_of_wallet_paged`.
+const MAX_PAGE_SIZE: u128 = 100;
+
+// Error messages. Each starts with a stable code clients can match on.
+const ERR_NONEXISTENT: &str = "USER-001: owner query for nonexistent token";
+const ERR_INCORRECT_OWNER: &str = "USER-002: transfer from incorrect owner";
+const ERR_WALLET_REGISTERED: &str = "USER-003: wallet already registered";
+const ERR_DUPLICATE_USER_ID: &str = "USER-004: duplicate user id";
+const ERR_WALLET_NOT_REGISTERED: &str = "USER-005: wallet not registered";
+const ERR_UNKNOWN_USER: &str = "USER-006: wallet maps to unknown user";
+const ERR_NO_PRODUCT_LIST: &str = "USER-007: no product list for user";
+const ERR_NOT_OWNER: &str = "USER-008: caller is not the contract owner";
+const ERR_STALE_NONCE: &str = "USER-009: stale or replayed nonce";
+const ERR_NOT_AUTHORIZED: &str =
+    "USER-010: caller is not the contract owner nor an authorized caller";
+const ERR_SAME_WALLET: &str = "USER-011: from and to are identical";
+const ERR_RECIPIENT_NOT_REGISTERED: &str = "USER-012: recipient wallet not registered";
+const ERR_SENDER_NOT_REGISTERED: &str = "USER-013: sender wallet not registered";
+const ERR_SENDER_NOT_HOLDER: &str = "USER-014: sender does not own product";
+const ERR_ALREADY_HOLDS_PRODUCT: &str = "USER-015: user already holds product";
+const ERR_PRODUCT_NOT_OWNED: &str = "USER-016: product not owned by wallet";
+const ERR_USER_NOT_REGISTERED: &str = "USER-017: user not registered";
+const ERR_USER_HOLDS_PRODUCTS: &str = "USER-018: user still owns products";
+const ERR_WALLET_TAKEN: &str = "USER-019: wallet already registered to another user";
+const ERR_USER_INCONSISTENT: &str = "USER-020: user indexes are inconsistent";
+const ERR_PRODUCT_HELD: &str = "USER-021: product already held by another user";
 
 /// A permission to transfer and approve NFTs given from an NFT owner to a separate address, called an operator.
 #[derive(ReadWriteState, CreateTypeSpec, PartialEq, Copy, Clone, Ord, PartialOrd, Eq)]
@@ -19,16 +49,76 @@ struct OperatorApproval {
     operator: Address,
 }
 
+/// A registered user and the wallet it acts through.
 #[derive(ReadWriteState, CreateTypeSpec, PartialEq, Clone, Ord, PartialOrd, Eq)]
-struct UserMetadata {
+pub struct UserMetadata {
     id: String,
     wallet: Address
 }
 
-#[derive(ReadWriteState, CreateTypeSpec, PartialEq, Clone, Ord, PartialOrd, Eq)]
-struct ProductMetadata {
+/// A user to register with `batch_mint_users`.
+#[derive(ReadWriteRPC, CreateTypeSpec, Clone)]
+pub struct UserRegistration {
+    /// External id of the user.
+    id: String,
+    /// Wallet the user acts through.
+    wallet: Address,
+}
+
+/// Reference to a product (an NFT) held by a user.
+///
+/// A product is identified by `contract_address` and `id` alone. The acquisition
+/// details are ignored when comparing, so a product can be looked up with [`ProductMetadata::key`].
+#[derive(ReadWriteState, ReadWriteRPC, CreateTypeSpec, Clone)]
+pub struct ProductMetadata {
     contract_address: Address,
     id: u128,
+    /// Block production time at which the current holder acquired the product.
+    acquired_at: i64,
+    /// Free-form note on how the current holder acquired the product.
+    note: String,
+}
+
+impl ProductMetadata {
+    /// Build the lookup key of a product, without acquisition details.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `contract_address`: [`Address`] The contract the product belongs to.
+    ///
+    /// * `id`: [`u128`] The id of the product in `contract_address`.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`ProductMetadata`] equal to every stored reference to the product.
+    pub fn key(contract_address: Address, id: u128) -> Self {
+        ProductMetadata {
+            contract_address,
+            id,
+            acquired_at: 0,
+            note: String::new(),
+        }
+    }
+}
+
+impl PartialEq for ProductMetadata {
+    fn eq(&self, other: &Self) -> bool {
+        self.contract_address == other.contract_address && self.id == other.id
+    }
+}
+
+impl Eq for ProductMetadata {}
+
+impl PartialOrd for ProductMetadata {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ProductMetadata {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.contract_address, self.id).cmp(&(other.contract_address, other.id))
+    }
 }
 
 /// State of the contract.
@@ -52,10 +142,63 @@ pub struct NFTContractState {
     contract_owner: Address,
     wallet_owner: SortedVecMap<Address, u128>,
     user_product_list: SortedVecMap<u128, SortedVec<ProductMetadata>>,
-    total_count: u128
+    /// Mapping from the external id of a user to its user id.
+    id_index: SortedVecMap<String, u128>,
+    /// Mapping from a product to the id of the user holding it.
+    product_owner: SortedVecMap<ProductMetadata, u128>,
+    /// Allocator for user ids. Never decreases, even when users are deregistered.
+    total_count: u128,
+    /// Number of currently registered users.
+    live_users: u128,
+    /// Number of products currently held by users.
+    live_products: u128,
+    /// Contract notified of product mints and transfers, if any.
+    event_listener: Option<Address>,
+    /// Contracts, such as NFT contracts, allowed to call the product actions besides the owner.
+    authorized_callers: SortedVec<Address>,
+    /// Mapping from caller to the nonce of the last product call it applied.
+    applied_nonce: SortedVecMap<Address, u128>,
+    /// Mapping from a product to the number of times it changed hands between users.
+    transfer_count: SortedVecMap<ProductMetadata, u128>,
+}
+
+/// Shortname of `product_minted` on the event listener.
+/// Called with the arguments `to, product_address, product_id`.
+#[inline]
+fn product_minted() -> Shortname {
+    Shortname::from_u32(0x01)
+}
+
+/// Shortname of `product_transferred` on the event listener.
+/// Called with the arguments `from, to, product_address, product_id`.
+#[inline]
+fn product_transferred() -> Shortname {
+    Shortname::from_u32(0x02)
 }
 
 impl NFTContractState {
+    /// Check whether an address is the owner of the contract.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `addr`: [`Address`] The address to check.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if `addr` owns the contract, false otherwise.
+    pub fn is_contract_owner(&self, addr: Address) -> bool {
+        self.contract_owner == addr
+    }
+
+    /// Get the owner of the contract.
+    ///
+    /// ### Returns:
+    ///
+    /// The [`Address`] of the contract owner.
+    pub fn contract_owner(&self) -> Address {
+        self.contract_owner
+    }
+
     /// Find the owner of an NFT.
     /// Throws if no such token exists.
     ///
@@ -69,7 +212,7 @@ impl NFTContractState {
     pub fn owner_of(&self, token_id: u128) -> Address {
         let owner_opt = self.owners.get(&token_id);
         match owner_opt {
-            None => panic!("MPC-721: owner query for nonexistent token"),
+            None => panic!("{}", ERR_NONEXISTENT),
             Some(owner) => *owner,
         }
     }
@@ -169,145 +312,1247 @@ impl NFTContractState {
     /// * `token_id`: [`u128`], The NFT to transfer
     pub fn _transfer(&mut self, from: Address, to: Address, token_id: u128) {
         if self.owner_of(token_id) != from {
-            panic!("MPC-721: transfer from incorrect owner")
+            panic!("{}", ERR_INCORRECT_OWNER)
         } else {
             // clear approvals from the previous owner
             self._approve(None, token_id);
             self.owners.insert(token_id, to);
         }
     }
-}
 
-/// Initial function to bootstrap the contracts state.
-///
-/// ### Parameters:
-///
-/// * `ctx`: [`ContractContext`], initial context.
-///
-/// * `name`: [`String`], A descriptive name for a collection of NFTs in this contract.
-///
-/// * `symbol`: [`String`], An abbreviated name for NFTs in this contract.
-///
-/// * `uri_template`: [`String`], Template for uri´s associated with NFTs in this contract.
-///
-/// ### Returns:
-///
-/// The new state object of type [`NFTContractState`].
-#[init]
-pub fn initialize(
-    ctx: ContractContext,
-    name: String,
-    symbol: String,
-    uri_template: String,
-) -> NFTContractState {
-    NFTContractState {
-        name,
-        symbol,
-        owners: SortedVecMap::new(),
-        token_approvals: SortedVecMap::new(),
-        operator_approvals: SortedVec::new(),
-        uri_template,
-        user_list: SortedVecMap::new(),
-        contract_owner: ctx.sender,
-        wallet_owner: SortedVecMap::new(),
-        user_product_list: SortedVecMap::new(),
-        total_count: 0
+    /// Mutates the state by registering a new user with an empty product list.
+    /// As opposed to {mint}, this imposes no restrictions on `ctx.sender`.
+    ///
+    /// Throws if `wallet` or `id` is already registered.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `id`: [`String`], The external id of the user
+    ///
+    /// * `wallet`: [`Address`], The wallet of the user
+    ///
+    /// ### Returns:
+    ///
+    /// The [`u128`] id allocated for the user.
+    pub fn _register_user(&mut self, id: String, wallet: Address) -> u128 {
+        if self.wallet_owner.contains_key(&wallet) {
+            panic!("{}", ERR_WALLET_REGISTERED)
+        }
+        if self.id_index.contains_key(&id) {
+            panic!("{}", ERR_DUPLICATE_USER_ID)
+        }
+        self.total_count += 1;
+        let user_id = self.total_count;
+
+        self.id_index.insert(id.clone(), user_id);
+        self.user_list.insert(user_id, UserMetadata { id, wallet });
+        self.wallet_owner.insert(wallet, user_id);
+        self.user_product_list.insert(user_id, SortedVec::new());
+        self.live_users += 1;
+        user_id
     }
-}
 
-/// Mints `token_id` and transfers it to an owner.
-///
-/// Requirements:
-///
-/// - `token_id` must not exist
-/// - `ctx.sender` owns the contract
-///
-/// ### Parameters:
-///
-/// * `ctx`: [`ContractContext`], the context for the action call.
-///
-/// * `state`: [`NFTContractState`], the current state of the contract.
-///
-/// * `to`: [`Address`], the owner of the minted token.
-///
-/// * `token_id`: [`u128`], The new id for the minted token.
-///
-/// ### Returns
-///
-/// The new state object of type [`NFTContractState`] with an updated ledger.
-#[action(shortname = 0x01)]
-pub fn mint(
-    ctx: ContractContext,
-    mut state: NFTContractState,
-    user_id: String,
-    wallet: Address,
-) -> NFTContractState {
-    if ctx.sender != state.contract_owner {
-        panic!("MPC-721: mint only callable by the contract owner")
-    } else {
-        state.total_count += 1;
-        let token_uri = UserMetadata {
-            id: user_id,
-            wallet: wallet
-        };
+    /// Get the number of currently registered users.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] count of users that have been minted and not deregistered.
+    pub fn total_users(&self) -> u128 {
+        self.live_users
+    }
+
+    /// Get the number of products currently held across all users.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] count of products that have been minted and not burned or cleared.
+    pub fn total_products(&self) -> u128 {
+        self.live_products
+    }
 
-        state.user_list.insert(state.total_count, token_uri);
-        state.wallet_owner.insert(wallet, state.total_count);
-        state
+    /// Check whether a wallet is registered to a user.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `wallet`: [`Address`] The wallet to check.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if `wallet` is registered, false otherwise.
+    pub fn is_registered(&self, wallet: Address) -> bool {
+        self.wallet_owner.contains_key(&wallet)
     }
-}
 
-#[action(shortname = 0x02)]
-pub fn transfer_product(
-    ctx: ContractContext,
-    mut state: NFTContractState,
-    from: Address,
-    to: Address,
-    product_address: Address,
-    product_id: u128
-) -> NFTContractState {
-    if ctx.sender != state.contract_owner {
-        panic!("MPC-721: mint only callable by the contract owner")
-    } else {
+    /// Check whether a user id is in use.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `user_id`: [`u128`] The id to check.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if `user_id` belongs to a registered user, false otherwise.
+    pub fn is_user(&self, user_id: u128) -> bool {
+        self.user_list.contains_key(&user_id)
+    }
 
-        let to_id = state.wallet_owner.get(&to).unwrap();
-        let to_product_list = state.user_product_list.get_mut(&to_id).unwrap();
+    /// Find the user id registered for an external id.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `id`: [`&str`] The external id of the user.
+    ///
+    /// ### Returns:
+    ///
+    /// An [`Option<u128>`] with the user id, or none if the external id is not registered.
+    pub fn user_id_by_external_id(&self, id: &str) -> Option<u128> {
+        self.id_index.get(&id.to_string()).copied()
+    }
 
-        let product_uri = ProductMetadata {
-            contract_address: product_address,
-            id: product_id
-        };
-        to_product_list.insert(product_uri.clone());
+    /// Look up a registered user by its id.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `user_id`: [`u128`] The id of the user.
+    ///
+    /// ### Returns:
+    ///
+    /// An [`Option<UserMetadata>`] for the user, or none if no such user exists.
+    pub fn user_by_id(&self, user_id: u128) -> Option<UserMetadata> {
+        self.user_list.get(&user_id).cloned()
+    }
 
-        let from_id = state.wallet_owner.get(&from).unwrap();
-        let from_product_list = state.user_product_list.get_mut(&from_id).unwrap();
-        from_product_list.remove(&product_uri.clone());
+    /// Look up the wallet of a user.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `user_id`: [`u128`] The id of the user.
+    ///
+    /// ### Returns:
+    ///
+    /// An [`Option<Address>`] with the user's wallet, or none if no such user exists.
+    pub fn wallet_of(&self, user_id: u128) -> Option<Address> {
+        self.user_list.get(&user_id).map(|user| user.wallet)
+    }
 
-        state
+    /// Look up the user registered to a wallet.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `wallet`: [`Address`] The wallet of the user.
+    ///
+    /// ### Returns:
+    ///
+    /// An [`Option<UserMetadata>`] for the user, or none if the wallet is not registered.
+    pub fn user_by_wallet(&self, wallet: Address) -> Option<UserMetadata> {
+        let user_id = self.wallet_owner.get(&wallet)?;
+        self.user_by_id(*user_id)
     }
-}
 
-#[action(shortname = 0x03)]
-pub fn mint_product(
-    ctx: ContractContext,
-    mut state: NFTContractState,
-    to: Address,
-    product_address: Address,
-    product_id: u128
-) -> NFTContractState {
-    if ctx.sender != state.contract_owner {
-        panic!("MPC-721: mint only callable by the contract owner")
-    } else {
+    /// Get the number of products held by a user.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `user_id`: [`u128`] The id of the user.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] count of the user's products, 0 if the user holds none or does not exist.
+    pub fn product_count_of_user(&self, user_id: u128) -> u128 {
+        match self.user_product_list.get(&user_id) {
+            None => 0,
+            Some(product_list) => product_list.len() as u128,
+        }
+    }
 
-        let to_id = state.wallet_owner.get(&to).unwrap();
-        let to_product_list = state.user_product_list.get_mut(&to_id).unwrap();
+    /// Get the number of products held by every registered user.
+    /// Walks all users, so this is an O(n) query meant for admin tooling.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`Vec<(u128, u128)>`] of `(user_id, product_count)` pairs, ordered by user id.
+    pub fn product_counts(&self) -> Vec<(u128, u128)> {
+        self.user_list
+            .iter()
+            .map(|(user_id, _)| (*user_id, self.product_count_of_user(*user_id)))
+            .collect()
+    }
 
-        let product_uri = ProductMetadata {
-            contract_address: product_address,
-            id: product_id
-        };
-        to_product_list.insert(product_uri.clone());
+    /// Get the number of products held by the user registered to a wallet.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `wallet`: [`Address`] The wallet of the user.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] count of the user's products, 0 if the wallet is not registered.
+    pub fn product_count_of_wallet(&self, wallet: Address) -> u128 {
+        match self.wallet_owner.get(&wallet) {
+            None => 0,
+            Some(user_id) => self.product_count_of_user(*user_id),
+        }
+    }
+
+    /// Check whether a user holds a product.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `user_id`: [`u128`] The id of the user.
+    ///
+    /// * `product`: [`&ProductMetadata`] The product to look for.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if the user's product list contains `product`, false otherwise.
+    pub fn user_has_product(&self, user_id: u128, product: &ProductMetadata) -> bool {
+        match self.user_product_list.get(&user_id) {
+            None => false,
+            Some(product_list) => product_list.contains(product),
+        }
+    }
+
+    /// Find the user holding a product.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `product`: [`ProductMetadata`] The product to look up.
+    ///
+    /// ### Returns:
+    ///
+    /// An [`Option<u128>`] with the id of the holding user, or none if no user holds the product.
+    pub fn owner_of_product(&self, product: ProductMetadata) -> Option<u128> {
+        self.product_owner.get(&product).copied()
+    }
+
+    /// Mutates the state by indexing a product that no user holds yet to `user_id`.
+    /// Throws if the product is already indexed, so an existing entry is never overwritten.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `product`: [`ProductMetadata`] The product to index.
+    ///
+    /// * `user_id`: [`u128`] The id of the user holding the product.
+    fn _index_product(&mut self, product: ProductMetadata, user_id: u128) {
+        if self.product_owner.contains_key(&product) {
+            panic!("{}", ERR_PRODUCT_HELD)
+        }
+        self.product_owner.insert(product, user_id);
+        self.live_products += 1;
+    }
+
+    /// Get the number of times a product changed hands between users.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `product`: [`ProductMetadata`] The product to look up.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`u128`] count of transfers of the product, 0 if it was never transferred.
+    pub fn product_transfer_count(&self, product: ProductMetadata) -> u128 {
+        self.transfer_count.get(&product).copied().unwrap_or(0)
+    }
 
-        state
+    /// Mutates the state by counting one more transfer of a product.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `product`: [`ProductMetadata`] The transferred product.
+    fn _count_transfer(&mut self, product: &ProductMetadata) {
+        let key = ProductMetadata::key(product.contract_address, product.id);
+        let count = self.product_transfer_count(key.clone());
+        self.transfer_count.insert(key, count + 1);
     }
-}
\ No newline at end of file
+
+    /// List the products held by the user registered to a wallet.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `wallet`: [`Address`] The wallet to list products for.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`Vec<ProductMetadata>`] of the products held, empty if the wallet is not registered.
+    pub fn products_of_wallet(&self, wallet: Address) -> Vec<ProductMetadata> {
+        let user_id = match self.wallet_owner.get(&wallet) {
+            None => return Vec::new(),
+            Some(user_id) => user_id,
+        };
+        match self.user_product_list.get(user_id) {
+            None => Vec::new(),
+            Some(product_list) => product_list.iter().cloned().collect(),
+        }
+    }
+
+    /// List the products held by the user registered to a wallet, reporting
+    /// inconsistencies between the wallet, user and product maps as errors.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `wallet`: [`Address`] The wallet to list products for.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`Vec<ProductMetadata>`] of the products held, or an error describing why
+    /// they could not be resolved.
+    pub fn products_of_wallet_checked(
+        &self,
+        wallet: Address,
+    ) -> Result<Vec<ProductMetadata>, String> {
+        let user_id = match self.wallet_owner.get(&wallet) {
+            None => return Err(ERR_WALLET_NOT_REGISTERED.to_string()),
+            Some(user_id) => *user_id,
+        };
+        if !self.user_list.contains_key(&user_id) {
+            return Err(format!("{} {}", ERR_UNKNOWN_USER, user_id));
+        }
+        match self.user_product_list.get(&user_id) {
+            None => Err(format!("{} {}", ERR_NO_PRODUCT_LIST, user_id)),
+            Some(product_list) => Ok(product_list.iter().cloned().collect()),
+        }
+    }
+
+    /// List one page of the products held by the user registered to a wallet.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `wallet`: [`Address`] The wallet to list products for.
+    ///
+    /// * `offset`: [`u128`] The number of products to skip.
+    ///
+    /// * `limit`: [`u128`] The maximum number of products to return, clamped to [`MAX_PAGE_SIZE`].
+    ///
+    /// ### Returns:
+    ///
+    /// A [`Vec<ProductMetadata>`] of the products in the page, empty past the end of the list
+    /// or if the wallet is not registered.
+    pub fn products_of_wallet_paged(
+        &self,
+        wallet: Address,
+        offset: u128,
+        limit: u128,
+    ) -> Vec<ProductMetadata> {
+        let user_id = match self.wallet_owner.get(&wallet) {
+            None => return Vec::new(),
+            Some(user_id) => user_id,
+        };
+        let offset = usize::try_from(offset).unwrap_or(usize::MAX);
+        let limit = limit.min(MAX_PAGE_SIZE) as usize;
+        match self.user_product_list.get(user_id) {
+            None => Vec::new(),
+            Some(product_list) => product_list.iter().skip(offset).take(limit).cloned().collect(),
+        }
+    }
+
+    /// Check that a user's wallet and products are indexed back to it.
+    ///
+    /// ### Parameters:
+    ///
+    /// * `user_id`: [`u128`] The id of the user.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`bool`] True if the user exists, its wallet maps to `user_id`, it has a product list,
+    /// every product in that list is indexed to `user_id`, and every product indexed to
+    /// `user_id` is in that list. False otherwise.
+    pub fn verify_user_consistency(&self, user_id: u128) -> bool {
+        let user = match self.user_list.get(&user_id) {
+            None => return false,
+            Some(user) => user,
+        };
+        if self.wallet_owner.get(&user.wallet) != Some(&user_id) {
+            return false;
+        }
+        let product_list = match self.user_product_list.get(&user_id) {
+            None => return false,
+            Some(product_list) => product_list,
+        };
+        product_list
+            .iter()
+            .all(|product| self.product_owner.get(product) == Some(&user_id))
+            && self
+                .product_owner
+                .iter()
+                .filter(|(_, owner_id)| **owner_id == user_id)
+                .all(|(product, _)| product_list.contains(product))
+    }
+
+    /// Check the relationships between the mappings of the state, for debugging state drift.
+    ///
+    /// ### Returns:
+    ///
+    /// A [`Vec<String>`] describing every violation found, empty when the state is consistent.
+    pub fn verify_invariants(&self) -> Vec<String> {
+        let mut violations = vec![];
+        for (wallet, user_id) in self.wallet_owner.iter() {
+            match self.user_list.get(user_id) {
+                None => violations.push(format!(
+                    "wallet {:?} maps to unknown user {}",
+                    wallet, user_id
+                )),
+                Some(user) if user.wallet != *wallet => violations.push(format!(
+                    "wallet {:?} maps to user {} of another wallet",
+                    wallet, user_id
+                )),
+                Some(_) => {}
+            }
+        }
+        for (user_id, user) in self.user_list.iter() {
+            if self.wallet_owner.get(&user.wallet) != Some(user_id) {
+                violations.push(format!("wallet of user {} does not map back to it", user_id));
+            }
+        }
+        for (product, user_id) in self.product_owner.iter() {
+            if !self.user_has_product(*user_id, product) {
+                violations.push(format!(
+                    "product {} of {:?} is indexed to user {} who does not hold it",
+                    product.id, product.contract_address, user_id
+                ));
+            }
+        }
+        for (user_id, product_list) in self.user_product_list.iter() {
+            for product in product_list.iter() {
+                if self.product_owner.get(product) != Some(user_id) {
+                    violations.push(format!(
+                        "product {} of {:?} held by user {} is not indexed to it",
+                        product.id, product.contract_address, user_id
+                    ));
+                }
+            }
+        }
+        if self.live_users != self.user_list.len() as u128 {
+            violations.push(format!(
+                "live_users is {} but {} users are registered",
+                self.live_users,
+                self.user_list.len()
+            ));
+        }
+        if self.live_products != self.product_owner.len() as u128 {
+            violations.push(format!(
+                "live_products is {} but {} products are indexed",
+                self.live_products,
+                self.product_owner.len()
+            ));
+        }
+        violations
+    }
+}
+
+/// Throws unless `sender` is the owner of the contract.
+/// Used by every action restricted to the contract owner.
+///
+/// ### Parameters:
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `sender`: [`Address`], the caller of the action.
+fn assert_owner(state: &NFTContractState, sender: Address) {
+    if !state.is_contract_owner(sender) {
+        panic!("{}", ERR_NOT_OWNER)
+    }
+}
+
+/// Throws unless `nonce` is greater than the last nonce applied for `sender`,
+/// and records it as applied. Protects the product calls against replays.
+///
+/// ### Parameters:
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `sender`: [`Address`], the caller of the action.
+///
+/// * `nonce`: [`u128`], the nonce given to the call.
+fn apply_nonce(state: &mut NFTContractState, sender: Address, nonce: u128) {
+    if let Some(last) = state.applied_nonce.get(&sender) {
+        if nonce <= *last {
+            panic!("{}", ERR_STALE_NONCE)
+        }
+    }
+    state.applied_nonce.insert(sender, nonce);
+}
+
+/// Throws unless `sender` is the owner of the contract or an authorized caller.
+/// Used by the product actions invoked by NFT contracts.
+///
+/// ### Parameters:
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `sender`: [`Address`], the caller of the action.
+fn assert_owner_or_authorized(state: &NFTContractState, sender: Address) {
+    if !state.is_contract_owner(sender) && !state.authorized_callers.contains(&sender) {
+        panic!("{}", ERR_NOT_AUTHORIZED)
+    }
+}
+
+/// Initial function to bootstrap the contracts state.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], initial context.
+///
+/// * `name`: [`String`], A descriptive name for a collection of NFTs in this contract.
+///
+/// * `symbol`: [`String`], An abbreviated name for NFTs in this contract.
+///
+/// * `uri_template`: [`String`], Template for uri´s associated with NFTs in this contract.
+///
+/// * `authorized_callers`: [`Vec<Address>`], NFT contracts allowed to call the product actions.
+///
+/// ### Returns:
+///
+/// The new state object of type [`NFTContractState`].
+#[init]
+pub fn initialize(
+    ctx: ContractContext,
+    name: String,
+    symbol: String,
+    uri_template: String,
+    authorized_callers: Vec<Address>,
+) -> NFTContractState {
+    let mut callers = SortedVec::new();
+    for caller in authorized_callers {
+        callers.insert(caller);
+    }
+    NFTContractState {
+        name,
+        symbol,
+        owners: SortedVecMap::new(),
+        token_approvals: SortedVecMap::new(),
+        operator_approvals: SortedVec::new(),
+        uri_template,
+        user_list: SortedVecMap::new(),
+        contract_owner: ctx.sender,
+        wallet_owner: SortedVecMap::new(),
+        user_product_list: SortedVecMap::new(),
+        id_index: SortedVecMap::new(),
+        product_owner: SortedVecMap::new(),
+        total_count: 0,
+        live_users: 0,
+        live_products: 0,
+        event_listener: None,
+        authorized_callers: callers,
+        applied_nonce: SortedVecMap::new(),
+        transfer_count: SortedVecMap::new(),
+    }
+}
+
+/// Mints `token_id` and transfers it to an owner.
+///
+/// Requirements:
+///
+/// - `token_id` must not exist
+/// - `ctx.sender` owns the contract
+/// - `wallet` must not already be registered to a user
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `to`: [`Address`], the owner of the minted token.
+///
+/// * `token_id`: [`u128`], The new id for the minted token.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x01)]
+pub fn mint(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    user_id: String,
+    wallet: Address,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    state._register_user(user_id, wallet);
+    state
+}
+
+/// Registers a list of users in one call, allocating sequential ids.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - when `strict` is set, no wallet or external id in `users` may already be registered
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `users`: [`Vec<UserRegistration>`], the users to register.
+///
+/// * `strict`: [`bool`], True to throw on already registered users, false to skip them.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x09)]
+pub fn batch_mint_users(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    users: Vec<UserRegistration>,
+    strict: bool,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    for user in users {
+        let duplicate =
+            state.wallet_owner.contains_key(&user.wallet) || state.id_index.contains_key(&user.id);
+        if !strict && duplicate {
+            continue;
+        }
+        state._register_user(user.id, user.wallet);
+    }
+    state
+}
+
+/// Moves a product from the user registered to `from` to the user registered to `to`.
+///
+/// Invoked by the NFT contract's `transfer_from`, which sends the arguments
+/// in the order `from, to, product_address, product_id, note, nonce`.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract or is an authorized caller
+/// - `nonce` is greater than the last nonce applied for `ctx.sender`
+/// - `from` and `to` are registered
+/// - the user registered to `from` holds the product
+///
+/// A move from a wallet to itself only applies the nonce, matching the NFT contract,
+/// where such a transfer changes nothing.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `from`: [`Address`], the wallet currently holding the product.
+///
+/// * `to`: [`Address`], the wallet receiving the product.
+///
+/// * `product_address`: [`Address`], the contract the product belongs to.
+///
+/// * `product_id`: [`u128`], the id of the product in `product_address`.
+///
+/// * `note`: [`String`], how the receiving user acquired the product, possibly empty.
+///
+/// * `nonce`: [`u128`], the replay protection nonce of the call.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the event listener, if set.
+#[action(shortname = 0x02)]
+pub fn transfer_product(
+    ctx: ContractContext,
+    state: NFTContractState,
+    from: Address,
+    to: Address,
+    product_address: Address,
+    product_id: u128,
+    note: String,
+    nonce: u128,
+) -> (NFTContractState, Vec<EventGroup>) {
+    let product = ProductMetadata {
+        note,
+        ..ProductMetadata::key(product_address, product_id)
+    };
+    move_product(ctx, state, from, to, product, nonce)
+}
+
+/// Moves a product between users, recording the block production time and the `note`
+/// of `product` as its acquisition details. Shared by {transfer_product} and
+/// {transfer_product_by_key}.
+fn move_product(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    from: Address,
+    to: Address,
+    product: ProductMetadata,
+    nonce: u128,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner_or_authorized(&state, ctx.sender);
+    apply_nonce(&mut state, ctx.sender, nonce);
+    if from == to {
+        return (state, vec![]);
+    }
+    let product_address = product.contract_address;
+    let product_id = product.id;
+    let product_uri = ProductMetadata {
+        acquired_at: ctx.block_production_time,
+        ..product
+    };
+
+    // validate everything up front, so no list is touched unless the transfer succeeds
+    let to_id = match state.wallet_owner.get(&to) {
+        None => panic!("{}", ERR_RECIPIENT_NOT_REGISTERED),
+        Some(to_id) => *to_id,
+    };
+    let from_id = match state.wallet_owner.get(&from) {
+        None => panic!("{}", ERR_SENDER_NOT_REGISTERED),
+        Some(from_id) => *from_id,
+    };
+    match state.user_product_list.get(&from_id) {
+        Some(list) if list.contains(&product_uri) => {}
+        _ => panic!("{}", ERR_SENDER_NOT_HOLDER),
+    }
+
+    state.user_product_list.get_mut(&from_id).unwrap().remove(&product_uri);
+    if !state.user_product_list.contains_key(&to_id) {
+        state.user_product_list.insert(to_id, SortedVec::new());
+    }
+    state.user_product_list.get_mut(&to_id).unwrap().insert(product_uri.clone());
+    // remove first, so the stored key carries the new acquisition details
+    state.product_owner.remove(&product_uri);
+    state._count_transfer(&product_uri);
+    state.product_owner.insert(product_uri, to_id);
+
+    let mut events = vec![];
+    if let Some(event_listener) = state.event_listener {
+        let mut event_group = EventGroup::builder();
+        event_group
+            .call(event_listener, product_transferred())
+            .argument(from)
+            .argument(to)
+            .argument(product_address)
+            .argument(product_id)
+            .done();
+        events.push(event_group.build());
+    }
+
+    (state, events)
+}
+
+/// Moves a product from the user registered to `from` to the user registered to `to`,
+/// identifying the product by its [`ProductMetadata`] key.
+///
+/// Behaves like {transfer_product}, and records the `note` of `product` as the
+/// acquisition note of the new holder.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `from`: [`Address`], the wallet currently holding the product.
+///
+/// * `to`: [`Address`], the wallet receiving the product.
+///
+/// * `product`: [`ProductMetadata`], the product to move. Its `acquired_at` is ignored.
+///
+/// * `nonce`: [`u128`], the replay protection nonce of the call.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the event listener, if set.
+#[action(shortname = 0x0C)]
+pub fn transfer_product_by_key(
+    ctx: ContractContext,
+    state: NFTContractState,
+    from: Address,
+    to: Address,
+    product: ProductMetadata,
+    nonce: u128,
+) -> (NFTContractState, Vec<EventGroup>) {
+    move_product(ctx, state, from, to, product, nonce)
+}
+
+/// Records a newly minted product for the user registered to `to`.
+/// Throws if that user already holds the product.
+///
+/// Invoked by the NFT contract's `mint` and `batch_mint`, which send the arguments
+/// in the order `to, product_address, product_id, status, mpg_time, exp_time, note, nonce`.
+/// The metadata is carried for consumers of the event and is not stored here.
+///
+/// Callable by the contract owner and by authorized callers.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `to`: [`Address`], the wallet receiving the product.
+///
+/// * `product_address`: [`Address`], the contract the product belongs to.
+///
+/// * `product_id`: [`u128`], the id of the product in `product_address`.
+///
+/// * `status`: [`String`], the status stored for the minted NFT.
+///
+/// * `mpg_time`: [`String`], the manufacturing time stored for the minted NFT.
+///
+/// * `exp_time`: [`String`], the expiry time stored for the minted NFT.
+///
+/// * `note`: [`String`], how the receiving user acquired the product, possibly empty.
+///
+/// * `nonce`: [`u128`], the replay protection nonce of the call, greater than the last
+/// nonce applied for `ctx.sender`.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the event listener, if set.
+#[action(shortname = 0x03)]
+pub fn mint_product(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    to: Address,
+    product_address: Address,
+    product_id: u128,
+    status: String,
+    mpg_time: String,
+    exp_time: String,
+    note: String,
+    nonce: u128,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner_or_authorized(&state, ctx.sender);
+    apply_nonce(&mut state, ctx.sender, nonce);
+    let to_id = match state.wallet_owner.get(&to) {
+        None => panic!("{}", ERR_RECIPIENT_NOT_REGISTERED),
+        Some(to_id) => *to_id,
+    };
+    let product_uri = ProductMetadata {
+        contract_address: product_address,
+        id: product_id,
+        acquired_at: ctx.block_production_time,
+        note,
+    };
+    if state.user_has_product(to_id, &product_uri) {
+        panic!("{}", ERR_ALREADY_HOLDS_PRODUCT)
+    }
+    if state.product_owner.contains_key(&product_uri) {
+        panic!("{}", ERR_PRODUCT_HELD)
+    }
+
+    if !state.user_product_list.contains_key(&to_id) {
+        state.user_product_list.insert(to_id, SortedVec::new());
+    }
+    let to_product_list = state.user_product_list.get_mut(&to_id).unwrap();
+    to_product_list.insert(product_uri.clone());
+    state._index_product(product_uri, to_id);
+
+    let mut events = vec![];
+    if let Some(event_listener) = state.event_listener {
+        let mut event_group = EventGroup::builder();
+        event_group
+            .call(event_listener, product_minted())
+            .argument(to)
+            .argument(product_address)
+            .argument(product_id)
+            .done();
+        events.push(event_group.build());
+    }
+
+    (state, events)
+}
+
+/// Replaces the reference to a product held by a user with a new product id.
+///
+/// Used when an NFT is burned and re-minted at a new id by the product contract,
+/// which would otherwise leave the old id behind in the user's list.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `wallet` is registered to a user
+/// - the user holds the product identified by `product_address` and `old_id`
+/// - no user holds the product identified by `product_address` and `new_id`
+///
+/// The acquisition details and transfer count of the product carry over to the new id.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `wallet`: [`Address`], the wallet of the user holding the product.
+///
+/// * `product_address`: [`Address`], the contract the product belongs to.
+///
+/// * `old_id`: [`u128`], the id the product was previously known by.
+///
+/// * `new_id`: [`u128`], the id the product has been re-minted at.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x04)]
+pub fn reissue_product(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    wallet: Address,
+    product_address: Address,
+    old_id: u128,
+    new_id: u128,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    let user_id = match state.wallet_owner.get(&wallet) {
+        None => panic!("{}", ERR_WALLET_NOT_REGISTERED),
+        Some(user_id) => *user_id,
+    };
+    if state.product_owner.contains_key(&ProductMetadata::key(product_address, new_id)) {
+        panic!("{}", ERR_PRODUCT_HELD)
+    }
+    let product_list = match state.user_product_list.get_mut(&user_id) {
+        None => panic!("{}", ERR_PRODUCT_NOT_OWNED),
+        Some(product_list) => product_list,
+    };
+
+    let old_product = match product_list
+        .iter()
+        .find(|product| **product == ProductMetadata::key(product_address, old_id))
+    {
+        None => panic!("{}", ERR_PRODUCT_NOT_OWNED),
+        Some(product) => product.clone(),
+    };
+    let new_product = ProductMetadata {
+        id: new_id,
+        ..old_product.clone()
+    };
+    product_list.remove(&old_product);
+    product_list.insert(new_product.clone());
+    state.product_owner.remove(&old_product);
+    state.product_owner.insert(new_product.clone(), user_id);
+    if let Some(count) = state.transfer_count.remove(&old_product) {
+        state.transfer_count.insert(new_product, count);
+    }
+
+    state
+}
+
+/// Removes a registered user together with its wallet mapping and product list.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `user_id` must exist
+/// - the user must not hold any products
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `user_id`: [`u128`], the id of the user to remove.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x05)]
+pub fn deregister_user(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    user_id: u128,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    let (wallet, external_id) = match state.user_list.get(&user_id) {
+        None => panic!("{}", ERR_USER_NOT_REGISTERED),
+        Some(user) => (user.wallet, user.id.clone()),
+    };
+    if let Some(product_list) = state.user_product_list.get(&user_id) {
+        if !product_list.is_empty() {
+            panic!("{}", ERR_USER_HOLDS_PRODUCTS)
+        }
+    }
+
+    state.user_list.remove(&user_id);
+    state.wallet_owner.remove(&wallet);
+    state.id_index.remove(&external_id);
+    state.user_product_list.remove(&user_id);
+    state.live_users -= 1;
+
+    state
+}
+
+/// Empties the product list of a registered user and removes the products from the
+/// product index, e.g. before closing the account with {deregister_user}.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `user_id` must exist
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `user_id`: [`u128`], the id of the user whose products are cleared.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x0D)]
+pub fn clear_products(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    user_id: u128,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    if !state.user_list.contains_key(&user_id) {
+        panic!("{}", ERR_USER_NOT_REGISTERED)
+    }
+    let products: Vec<ProductMetadata> = match state.user_product_list.get(&user_id) {
+        None => Vec::new(),
+        Some(product_list) => product_list.iter().cloned().collect(),
+    };
+    for product in products.iter() {
+        if state.product_owner.remove(product).is_some() {
+            state.live_products -= 1;
+        }
+    }
+    state.user_product_list.insert(user_id, SortedVec::new());
+
+    state
+}
+
+/// Moves a registered user to a new wallet address, e.g. after losing access to the old one.
+///
+/// The user's products stay keyed by its user id, so lookups by the new wallet resolve
+/// them. Throws if the user's product indexes disagree, see {verify_user_consistency}.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `user_id` must exist
+/// - `new_wallet` must not be registered to a different user
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `user_id`: [`u128`], the id of the user to update.
+///
+/// * `new_wallet`: [`Address`], the wallet the user is moved to.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x06)]
+pub fn update_wallet(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    user_id: u128,
+    new_wallet: Address,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    if let Some(owner_id) = state.wallet_owner.get(&new_wallet) {
+        if *owner_id != user_id {
+            panic!("{}", ERR_WALLET_TAKEN)
+        }
+    }
+    let user = match state.user_list.get_mut(&user_id) {
+        None => panic!("{}", ERR_USER_NOT_REGISTERED),
+        Some(user) => user,
+    };
+    let old_wallet = user.wallet;
+    user.wallet = new_wallet;
+
+    state.wallet_owner.remove(&old_wallet);
+    state.wallet_owner.insert(new_wallet, user_id);
+    if !state.verify_user_consistency(user_id) {
+        panic!("{}", ERR_USER_INCONSISTENT)
+    }
+
+    state
+}
+
+/// Removes burned products from the users holding them.
+///
+/// Invoked by the NFT contract's `burn` and `burn_batch`, which send the arguments
+/// in the order `product_address, product_ids`. Products no user holds are skipped.
+///
+/// Callable by the contract owner and by authorized callers.
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `product_address`: [`Address`], the contract the products belong to.
+///
+/// * `product_ids`: [`Vec<u128>`], the ids of the burned products in `product_address`.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x07)]
+pub fn burn_product(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    product_address: Address,
+    product_ids: Vec<u128>,
+) -> NFTContractState {
+    assert_owner_or_authorized(&state, ctx.sender);
+    for product_id in product_ids {
+        let product = ProductMetadata::key(product_address, product_id);
+        if let Some(user_id) = state.product_owner.remove(&product) {
+            state.live_products -= 1;
+            if let Some(product_list) = state.user_product_list.get_mut(&user_id) {
+                product_list.remove(&product);
+            }
+        }
+    }
+    state
+}
+
+/// Allows or disallows a contract, such as an NFT contract, to call the product actions.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `caller`: [`Address`], the contract to authorize or deauthorize.
+///
+/// * `authorized`: [`bool`], true to authorize `caller`, false to remove it.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x0B)]
+pub fn set_authorized_caller(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    caller: Address,
+    authorized: bool,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    if authorized {
+        state.authorized_callers.insert(caller);
+    } else {
+        state.authorized_callers.remove(&caller);
+    }
+    state
+}
+
+/// Sets the contract notified of product mints and transfers.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `event_listener`: [`Option<Address>`], the new listener, or none to stop sending events.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger.
+#[action(shortname = 0x08)]
+pub fn set_event_listener(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    event_listener: Option<Address>,
+) -> NFTContractState {
+    assert_owner(&state, ctx.sender);
+    state.event_listener = event_listener;
+    state
+}
+
+/// Moves every product held by the user registered to `from` to the user registered to `to`.
+///
+/// Requirements:
+///
+/// - `ctx.sender` owns the contract
+/// - `from` and `to` are registered and differ
+///
+/// ### Parameters:
+///
+/// * `ctx`: [`ContractContext`], the context for the action call.
+///
+/// * `state`: [`NFTContractState`], the current state of the contract.
+///
+/// * `from`: [`Address`], the wallet currently holding the products.
+///
+/// * `to`: [`Address`], the wallet receiving the products.
+///
+/// ### Returns
+///
+/// The new state object of type [`NFTContractState`] with an updated ledger,
+/// and an event notifying the event listener of every moved product, if set.
+#[action(shortname = 0x0A)]
+pub fn move_all_products(
+    ctx: ContractContext,
+    mut state: NFTContractState,
+    from: Address,
+    to: Address,
+) -> (NFTContractState, Vec<EventGroup>) {
+    assert_owner(&state, ctx.sender);
+    if from == to {
+        panic!("{}", ERR_SAME_WALLET)
+    }
+    let to_id = match state.wallet_owner.get(&to) {
+        None => panic!("{}", ERR_RECIPIENT_NOT_REGISTERED),
+        Some(to_id) => *to_id,
+    };
+    let from_id = match state.wallet_owner.get(&from) {
+        None => panic!("{}", ERR_SENDER_NOT_REGISTERED),
+        Some(from_id) => *from_id,
+    };
+
+    let products: Vec<ProductMetadata> = match state.user_product_list.get(&from_id) {
+        None => Vec::new(),
+        Some(product_list) => product_list
+            .iter()
+            .map(|product| ProductMetadata {
+                acquired_at: ctx.block_production_time,
+                note: String::new(),
+                ..product.clone()
+            })
+            .collect(),
+    };
+    if !state.user_product_list.contains_key(&to_id) {
+        state.user_product_list.insert(to_id, SortedVec::new());
+    }
+    let to_product_list = state.user_product_list.get_mut(&to_id).unwrap();
+    for product in products.iter() {
+        to_product_list.insert(product.clone());
+    }
+    state.user_product_list.insert(from_id, SortedVec::new());
+    for product in products.iter() {
+        state.product_owner.remove(product);
+        state._count_transfer(product);
+        state.product_owner.insert(product.clone(), to_id);
+    }
+
+    let mut events = vec![];
+    if let Some(event_listener) = state.event_listener.filter(|_| !products.is_empty()) {
+        let mut event_group = EventGroup::builder();
+        for product in products {
+            event_group
+                .call(event_listener, product_transferred())
+                .argument(from)
+                .argument(to)
+                .argument(product.contract_address)
+                .argument(product.id)
+                .done();
+        }
+        events.push(event_group.build());
+    }
+
+    (state, events)
+}